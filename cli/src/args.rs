@@ -0,0 +1,301 @@
+use chrono::{DateTime, Local, NaiveDate, NaiveTime, TimeZone, Utc};
+use clap::{Args, ValueEnum};
+use color_eyre::eyre::{eyre, Result};
+use std::cell::OnceCell;
+use std::io::IsTerminal;
+use std::path::PathBuf;
+use timex_core::git::diff::WalkOptions;
+use timex_core::git::GitCommit;
+use timex_core::project::workload::Workload;
+use timex_core::project::manual::{overlay, ManualEntries};
+use timex_core::project::sync::SyncState;
+use timex_core::project::timer::TimerState;
+use timex_core::project::{Project, TimeEntry};
+use timex_core::render::diff_html::DiffLayout;
+use timex_core::render::highlight::Highlighter;
+use timex_core::render::ExportFormat;
+use timex_core::store::Store;
+use timex_core::summary::ProjectActivity;
+
+/// Options shared by every subcommand.
+#[derive(Debug, Args)]
+pub struct GlobalArgs {
+    /// Path to the workload configuration
+    #[arg(long, global = true, default_value = "workload.toml")]
+    pub config: PathBuf,
+
+    /// Only include the project with this name or code
+    #[arg(long, global = true)]
+    pub project: Option<String>,
+
+    /// Only include work from this date (YYYY-MM-DD, local time) or RFC 3339 timestamp on
+    #[arg(long, global = true, value_parser = parse_since)]
+    pub since: Option<DateTime<Utc>>,
+
+    /// Only include work up to this date (YYYY-MM-DD, inclusive) or RFC 3339 timestamp
+    #[arg(long, global = true, value_parser = parse_until)]
+    pub until: Option<DateTime<Utc>>,
+
+    /// Output format
+    #[arg(long, global = true, value_enum, default_value_t = Format::Text)]
+    pub format: Format,
+
+    /// Path to the local store [default: timex.db next to the configuration]
+    #[arg(long, global = true)]
+    pub store: Option<PathBuf>,
+
+    /// When to colour and syntax highlight terminal output
+    #[arg(long, global = true, value_enum, default_value_t = ColorChoice::Auto)]
+    pub color: ColorChoice,
+
+    /// Path to the manual time entries, TOML or JSON [default: entries.toml next to the configuration]
+    #[arg(long, global = true)]
+    pub entries: Option<PathBuf>,
+
+    /// Neither read from nor write to the local store
+    #[arg(long, global = true, conflicts_with = "store")]
+    pub no_store: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Format {
+    /// Human-readable, coloured terminal output
+    Text,
+    Json,
+    Csv,
+    Markdown,
+    /// A standalone HTML document
+    Html,
+}
+
+impl Format {
+    /// The structured export format, or `None` for terminal output.
+    pub fn export(self) -> Option<ExportFormat> {
+        match self {
+            Format::Text => None,
+            Format::Json => Some(ExportFormat::Json),
+            Format::Csv => Some(ExportFormat::Csv),
+            Format::Markdown => Some(ExportFormat::Markdown),
+            Format::Html => Some(ExportFormat::Html),
+        }
+    }
+}
+
+/// How diffs are laid out in HTML output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Layout {
+    Unified,
+    SideBySide,
+}
+
+impl From<Layout> for DiffLayout {
+    fn from(layout: Layout) -> Self {
+        match layout {
+            Layout::Unified => DiffLayout::Unified,
+            Layout::SideBySide => DiffLayout::SideBySide,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ColorChoice {
+    /// Colour when writing to a terminal and `NO_COLOR` is unset
+    Auto,
+    Always,
+    Never,
+}
+
+impl ColorChoice {
+    pub fn enabled(self) -> bool {
+        match self {
+            ColorChoice::Auto => std::io::stdout().is_terminal() && std::env::var_os("NO_COLOR").is_none(),
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+        }
+    }
+}
+
+/// The loaded configuration together with the options of the current invocation.
+pub struct Context {
+    pub workload: Workload,
+    pub args: GlobalArgs,
+    /// The local store, unless disabled with `--no-store`.
+    pub store: Option<Store>,
+    /// Syntax highlighter for terminal diffs, if colour is enabled.
+    pub highlighter: Option<Highlighter>,
+    /// Time recorded by hand, merged into the inferred time entries; read on first use.
+    manual: OnceCell<ManualEntries>,
+    /// The punch clock, whose stopped timers are merged into the inferred time entries too; read on
+    /// first use.
+    timer: OnceCell<TimerState>,
+}
+
+impl Context {
+    pub fn load(args: GlobalArgs) -> Result<Self> {
+        let path = args.config.to_string_lossy().into_owned();
+        let workload = Workload::from_toml_file(&path)
+            .map_err(|e| eyre!("Failed to load {}: {}", path, e))?;
+        let store = if args.no_store {
+            None
+        } else {
+            let path = args.store.clone().unwrap_or_else(|| args.config.with_file_name("timex.db"));
+            let store = Store::open(&path).map_err(|e| eyre!("Failed to open {}: {}", path.display(), e))?;
+            Some(store)
+        };
+
+        let color = args.color.enabled() && args.format == Format::Text;
+        colored::control::set_override(color);
+        let highlighter = match color {
+            true => Highlighter::from_config(&workload.highlight)?,
+            false => None,
+        };
+
+        Ok(Context {
+            workload,
+            args,
+            store,
+            highlighter,
+            manual: OnceCell::new(),
+            timer: OnceCell::new(),
+        })
+    }
+
+    /// Where the punch clock is kept: `timex-timer.json` next to the configuration.
+    pub fn timer_path(&self) -> PathBuf {
+        self.args.config.with_file_name("timex-timer.json")
+    }
+
+    /// The punch clock at [`Context::timer_path`], read the first time it is needed.
+    pub fn timer_state(&self) -> Result<&TimerState> {
+        if let Some(timer) = self.timer.get() {
+            return Ok(timer);
+        }
+        let path = self.timer_path();
+        let timer = TimerState::load(&path).map_err(|e| eyre!("Failed to load {}: {}", path.display(), e))?;
+        Ok(self.timer.get_or_init(|| timer))
+    }
+
+    /// Where manual entries are kept: `--entries`, or `entries.toml` next to the configuration unless
+    /// only `entries.json` exists.
+    pub fn entries_path(&self) -> PathBuf {
+        self.args.entries.clone().unwrap_or_else(|| {
+            let toml = self.args.config.with_file_name("entries.toml");
+            let json = self.args.config.with_file_name("entries.json");
+            if !toml.exists() && json.exists() { json } else { toml }
+        })
+    }
+
+    /// The manual entries at [`Context::entries_path`], read and validated the first time they are needed.
+    pub fn manual_entries(&self) -> Result<&ManualEntries> {
+        if let Some(manual) = self.manual.get() {
+            return Ok(manual);
+        }
+        let path = self.entries_path();
+        let manual = ManualEntries::load(&path)
+            .and_then(|manual| manual.validate(&self.workload.projects).map(|_| manual))
+            .map_err(|e| eyre!("Failed to load {}: {}", path.display(), e))?;
+        Ok(self.manual.get_or_init(|| manual))
+    }
+
+    /// Where the last processed tip of every ref is kept: `timex-state.json` next to the configuration.
+    pub fn sync_state_path(&self) -> PathBuf {
+        self.args.config.with_file_name("timex-state.json")
+    }
+
+    /// The sync state at [`Context::sync_state_path`].
+    pub fn sync_state(&self) -> Result<SyncState> {
+        let path = self.sync_state_path();
+        SyncState::load(&path).map_err(|e| eyre!("Failed to load {}: {}", path.display(), e))
+    }
+
+    /// The projects selected by `--project`, failing if the filter matches none.
+    pub fn projects(&self) -> Result<Vec<&Project>> {
+        let projects = self.workload.select_projects(self.args.project.as_deref());
+        if projects.is_empty() {
+            if let Some(filter) = &self.args.project {
+                return Err(eyre!("No project named or coded '{}' in {}", filter, self.args.config.display()));
+            }
+        }
+        Ok(projects)
+    }
+
+    /// The user's commits within `--since`/`--until`, optionally restricted to `rev`.
+    pub fn walk_options(&self, rev: Option<String>) -> WalkOptions {
+        let mut options = self.workload.walk_options();
+        options.since = self.args.since;
+        options.until = self.args.until;
+        options.revspec = rev;
+        options
+    }
+
+    /// The commits on `project`, recorded in the store on the way.
+    pub fn project_commits(&self, project: &Project, options: &WalkOptions) -> Result<Vec<GitCommit>> {
+        let commits = self.workload.project_commits(project, options)?;
+        if let Some(store) = &self.store {
+            store.save_commits(&project.code, &commits)?;
+        }
+        Ok(commits)
+    }
+
+    /// The activity on `project`, recorded in the store on the way.
+    pub fn project_activity(&self, project: &Project, options: &WalkOptions) -> Result<ProjectActivity> {
+        let activity = self.workload.project_activity(project, options)?;
+        if let Some(store) = &self.store {
+            store.save_activity(&activity)?;
+        }
+        Ok(activity)
+    }
+
+    /// The highlighter for HTML output, which does not depend on the terminal.
+    pub fn html_highlighter(&self) -> Result<Option<Highlighter>> {
+        Ok(Highlighter::from_config(&self.workload.highlight)?)
+    }
+
+    /// The time entries on `project`, inferred and merged with stopped timers and the manual
+    /// entries within the walked period, recorded in the store on the way.
+    ///
+    /// With a store, only history new since the last run is walked and the sync state is advanced.
+    pub fn project_time_entries(&self, project: &Project, options: &WalkOptions) -> Result<Vec<TimeEntry>> {
+        let inferred = match &self.store {
+            Some(store) => {
+                let mut state = self.sync_state()?;
+                let inferred = self.workload.incremental_time_entries(project, options, &mut state, store)?;
+                state.save(self.sync_state_path())?;
+                inferred
+            }
+            None => self.workload.project_time_entries(project, options)?,
+        };
+        let timed = overlay(inferred, self.timer_state()?.project_entries(&project.code).cloned());
+        let mut entries = self.manual_entries()?.merge(project, timed, &Local)?;
+        entries.retain(|entry| {
+            options.since.is_none_or(|since| entry.end_time > since)
+                && options.until.is_none_or(|until| entry.start_time < until)
+        });
+        if let Some(store) = &self.store {
+            store.save_time_entries(&entries)?;
+        }
+        Ok(entries)
+    }
+}
+
+fn parse_since(value: &str) -> Result<DateTime<Utc>, String> {
+    parse_date(value, NaiveTime::MIN)
+}
+
+fn parse_until(value: &str) -> Result<DateTime<Utc>, String> {
+    parse_date(value, NaiveTime::from_hms_opt(23, 59, 59).unwrap())
+}
+
+/// Parses an RFC 3339 timestamp, or a local calendar date at `time_of_day`.
+fn parse_date(value: &str, time_of_day: NaiveTime) -> Result<DateTime<Utc>, String> {
+    if let Ok(datetime) = DateTime::parse_from_rfc3339(value) {
+        return Ok(datetime.to_utc());
+    }
+    let date = NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .map_err(|_| format!("expected YYYY-MM-DD or an RFC 3339 timestamp, got '{}'", value))?;
+    Local
+        .from_local_datetime(&date.and_time(time_of_day))
+        .earliest()
+        .map(|datetime| datetime.to_utc())
+        .ok_or_else(|| format!("'{}' does not exist in the local timezone", value))
+}
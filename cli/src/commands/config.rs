@@ -0,0 +1,14 @@
+use crate::args::{Context, Format};
+use color_eyre::eyre::{eyre, Result};
+
+pub fn run(ctx: &Context) -> Result<()> {
+    match ctx.args.format {
+        Format::Text => {
+            println!("# {}", ctx.args.config.display());
+            print!("{}", toml::to_string_pretty(&ctx.workload)?);
+        }
+        Format::Json => println!("{}", serde_json::to_string_pretty(&ctx.workload)?),
+        format => return Err(eyre!("config can only be printed as text or json, not {:?}", format)),
+    }
+    Ok(())
+}
@@ -0,0 +1,33 @@
+use crate::args::Context;
+use color_eyre::eyre::Result;
+use colored::*;
+use timex_core::render::diff_html::{self, DiffLayout};
+use timex_core::render::{render, ExportFormat};
+
+pub fn run(ctx: &Context, rev: Option<String>, layout: DiffLayout) -> Result<()> {
+    let options = ctx.walk_options(rev);
+
+    if let Some(format) = ctx.args.format.export() {
+        let mut diffs = Vec::new();
+        for project in ctx.projects()? {
+            diffs.extend(ctx.project_activity(project, &options)?.diffs);
+        }
+        match format {
+            ExportFormat::Html => print!("{}", diff_html::render("Diffs", &diffs, ctx.html_highlighter()?.as_ref(), layout)),
+            format => print!("{}", render("Diffs", &diffs, format)?),
+        }
+        return Ok(());
+    }
+
+    for project in ctx.projects()? {
+        println!("{} {} ({})\n", "==".bright_black(), project.name.bold(), project.code);
+        let activity = ctx.project_activity(project, &options)?;
+        for diff in &activity.diffs {
+            match &ctx.highlighter {
+                Some(highlighter) => println!("{}", highlighter.terminal(diff)),
+                None => println!("{}", diff),
+            }
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,83 @@
+use crate::args::Context;
+use chrono::NaiveDate;
+use color_eyre::eyre::Result;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use timex_core::render::{render, Tabular};
+
+/// Hours on one project and ticket on one calendar day where the work was done, inferred and manual.
+#[derive(Debug, Serialize)]
+struct DayTotal {
+    date: NaiveDate,
+    project: String,
+    code: String,
+    task: Option<String>,
+    hours: f64,
+}
+
+impl Tabular for DayTotal {
+    const HEADERS: &'static [&'static str] = &["date", "project", "code", "task", "hours"];
+
+    fn rows(&self) -> Vec<Vec<String>> {
+        vec![vec![
+            self.date.to_string(),
+            self.project.clone(),
+            self.code.clone(),
+            self.task.clone().unwrap_or_default(),
+            format!("{:.2}", self.hours),
+        ]]
+    }
+}
+
+pub fn run(ctx: &Context) -> Result<()> {
+    let options = ctx.walk_options(None);
+    let mut totals = Vec::new();
+
+    for project in ctx.projects()? {
+        let mut days: BTreeMap<(NaiveDate, Option<String>), f64> = BTreeMap::new();
+        for entry in ctx.project_time_entries(project, &options)? {
+            let day = entry.work_day();
+            *days.entry((day, entry.task.clone())).or_default() += hours(entry.duration());
+        }
+        totals.extend(days.into_iter().map(|((date, task), hours)| DayTotal {
+            date,
+            project: project.name.clone(),
+            code: project.code.clone(),
+            task,
+            hours,
+        }));
+    }
+
+    if let Some(format) = ctx.args.format.export() {
+        print!("{}", render("Hours", &totals, format)?);
+        return Ok(());
+    }
+
+    let mut grand_total = 0.0;
+    for project in ctx.projects()? {
+        let mut tasks: BTreeMap<Option<&str>, f64> = BTreeMap::new();
+        let mut days: BTreeMap<NaiveDate, f64> = BTreeMap::new();
+        for total in totals.iter().filter(|total| total.code == project.code) {
+            *tasks.entry(total.task.as_deref()).or_default() += total.hours;
+            *days.entry(total.date).or_default() += total.hours;
+        }
+        let total: f64 = days.values().sum();
+        println!("{} ({}): {:.2}h", project.name, project.code, total);
+        if tasks.keys().any(Option::is_some) {
+            for (task, hours) in tasks {
+                println!("  {}: {:.2}h", task.unwrap_or("no ticket"), hours);
+            }
+        }
+        for (date, hours) in days {
+            println!("  {}  {:>6.2}h", date, hours);
+        }
+        grand_total += total;
+    }
+
+    println!("Total: {:.2}h", grand_total);
+    Ok(())
+}
+
+pub fn hours(duration: chrono::Duration) -> f64 {
+    duration.num_seconds() as f64 / 3600.0
+}
@@ -0,0 +1,25 @@
+use crate::args::Context;
+use color_eyre::eyre::Result;
+use colored::*;
+use timex_core::render::render;
+
+pub fn run(ctx: &Context, rev: Option<String>) -> Result<()> {
+    let options = ctx.walk_options(rev);
+
+    if let Some(format) = ctx.args.format.export() {
+        let mut commits = Vec::new();
+        for project in ctx.projects()? {
+            commits.extend(ctx.project_commits(project, &options)?);
+        }
+        print!("{}", render("Commits", &commits, format)?);
+        return Ok(());
+    }
+
+    for project in ctx.projects()? {
+        println!("{} {} ({})\n", "==".bright_black(), project.name.bold(), project.code);
+        for commit in ctx.project_commits(project, &options)? {
+            print!("{}", commit);
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,8 @@
+pub mod config;
+pub mod diff;
+pub mod hours;
+pub mod log;
+pub mod report;
+pub mod sync;
+pub mod timer;
+pub mod timesheet;
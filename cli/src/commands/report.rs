@@ -0,0 +1,59 @@
+use crate::args::Context;
+use color_eyre::eyre::Result;
+use colored::*;
+use timex_core::render::diff_html::{self, DiffLayout};
+use timex_core::render::{render, ExportFormat};
+use timex_core::summary::{summarizer_for, CachedSummarizer, ProjectSummary, Summarizer};
+
+pub async fn run(ctx: &Context, offline: bool, layout: DiffLayout) -> Result<()> {
+    let summarizer = summarizer_for(&ctx.workload.llm, offline)?;
+    let summarizer: Box<dyn Summarizer + '_> = match &ctx.store {
+        Some(store) => Box::new(CachedSummarizer::new(summarizer.as_ref(), store)),
+        None => summarizer,
+    };
+    let options = ctx.walk_options(None);
+
+    // With a store, only history new since the last run is walked; older commits come from the store.
+    let activities = match &ctx.store {
+        Some(store) => {
+            let mut state = ctx.sync_state()?;
+            let projects = ctx.projects()?;
+            let activities = ctx.workload.collect_incremental_activity(&projects, &options, &mut state, store).await?;
+            state.save(ctx.sync_state_path())?;
+            activities
+        }
+        None => ctx.workload.collect_activity(&ctx.projects()?, &options).await?,
+    };
+    let mut summaries = Vec::new();
+    for activity in &activities {
+        summaries.push(ProjectSummary {
+            project: activity.project.name.clone(),
+            code: activity.project.code.clone(),
+            summary: summarizer.summarize(activity).await?.trim_end().to_string(),
+        });
+    }
+
+    match ctx.args.format.export() {
+        Some(ExportFormat::Html) => {
+            let projects: Vec<_> = summaries
+                .iter()
+                .zip(&activities)
+                .map(|(summary, activity)| (summary, activity.diffs.as_slice()))
+                .collect();
+            let highlighter = ctx.html_highlighter()?;
+            print!("{}", diff_html::render_report("Report", &projects, highlighter.as_ref(), layout));
+            return Ok(());
+        }
+        Some(format) => {
+            print!("{}", render("Report", &summaries, format)?);
+            return Ok(());
+        }
+        None => {}
+    }
+
+    for summary in summaries {
+        println!("{} {} ({})\n", "==".bright_black(), summary.project.bold(), summary.code);
+        println!("{}\n", summary.summary);
+    }
+    Ok(())
+}
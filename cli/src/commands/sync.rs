@@ -0,0 +1,41 @@
+use crate::args::Context;
+use color_eyre::eyre::{eyre, Result};
+use colored::*;
+use std::path::PathBuf;
+use timex_core::project::sync::{RefUpdate, SyncState};
+use timex_core::render::render;
+
+pub fn run(ctx: &Context, state_path: Option<PathBuf>, full: bool) -> Result<()> {
+    let path = state_path.unwrap_or_else(|| ctx.sync_state_path());
+    let mut state = SyncState::load(&path).map_err(|e| eyre!("Failed to load {}: {}", path.display(), e))?;
+
+    let mut commits = Vec::new();
+    for project in ctx.projects()? {
+        if full {
+            state.forget(&project.code);
+        }
+        let sync = ctx.workload.sync_project(project, &mut state)?;
+        if let Some(store) = &ctx.store {
+            store.save_activity(&sync.activity)?;
+        }
+        // Saved after every project, so an interrupted run keeps the progress made so far.
+        state.save(&path)?;
+
+        if ctx.args.format.export().is_none() {
+            println!("{} ({}): {} new commits", project.name.bold(), project.code, sync.activity.commits.len());
+            for (name, update) in &sync.updates {
+                match update {
+                    RefUpdate::ForcePushed => println!("  {} {}", "force-pushed".yellow(), name),
+                    RefUpdate::Deleted => println!("  {} {}", "deleted".red(), name),
+                    RefUpdate::Created | RefUpdate::FastForward => {}
+                }
+            }
+        }
+        commits.extend(sync.activity.commits);
+    }
+
+    if let Some(format) = ctx.args.format.export() {
+        print!("{}", render("New commits", &commits, format)?);
+    }
+    Ok(())
+}
@@ -0,0 +1,133 @@
+use super::hours::hours;
+use crate::args::{Context, Format};
+use chrono::{DateTime, Local, SubsecRound, Utc};
+use color_eyre::eyre::{eyre, Result};
+use colored::*;
+use serde::Serialize;
+use timex_core::project::timer::{RunningTimer, TimerCheck, TimerState};
+use timex_core::project::{Project, TimeEntry};
+use timex_core::render::render;
+
+/// What `status` reports as JSON.
+#[derive(Debug, Serialize)]
+struct TimerStatus {
+    running: Option<RunningTimer>,
+    check: Option<TimerCheck>,
+}
+
+pub fn start(ctx: &Context, project: &str, task: Option<String>) -> Result<()> {
+    let project = find_project(ctx, project)?;
+    let running = TimerState::update(ctx.timer_path(), |state| {
+        state.start(project, task, now())?;
+        Ok(state.running.clone().expect("just started"))
+    })?;
+
+    if ctx.args.format == Format::Text {
+        println!("Started {} at {}", describe(&running), running.start_time.with_timezone(&Local).format("%H:%M"));
+    }
+    Ok(())
+}
+
+pub fn stop(ctx: &Context) -> Result<()> {
+    let (running, entry) = TimerState::update(ctx.timer_path(), |state| {
+        let running = state.running.clone();
+        let entry = state.stop(&ctx.workload.projects, now())?;
+        Ok((running.expect("just stopped"), entry))
+    })?;
+    finish(ctx, &running, entry)
+}
+
+pub fn switch(ctx: &Context, project: &str, task: Option<String>) -> Result<()> {
+    let project = find_project(ctx, project)?;
+    let (running, entry, started) = TimerState::update(ctx.timer_path(), |state| {
+        let running = state.running.clone();
+        let entry = state.switch(&ctx.workload.projects, project, task, now())?;
+        Ok((running.expect("just stopped"), entry, state.running.clone().expect("just started")))
+    })?;
+    finish(ctx, &running, entry)?;
+
+    if ctx.args.format == Format::Text {
+        println!("Started {}", describe(&started));
+    }
+    Ok(())
+}
+
+pub fn status(ctx: &Context) -> Result<()> {
+    let running = load(ctx)?.running;
+    let check = match &running {
+        Some(running) => Some(ctx.workload.check_timer(running, now())?),
+        None => None,
+    };
+
+    match ctx.args.format {
+        Format::Text => match (&running, &check) {
+            (Some(running), Some(check)) => {
+                let elapsed = hours(now() - running.start_time);
+                println!(
+                    "Running: {} since {} ({:.2}h)",
+                    describe(running),
+                    running.start_time.with_timezone(&Local).format("%Y-%m-%d %H:%M"),
+                    elapsed
+                );
+                print_check(running, check);
+            }
+            _ => println!("No timer running"),
+        },
+        Format::Json => println!("{}", serde_json::to_string_pretty(&TimerStatus { running, check })?),
+        format => return Err(eyre!("status can only be printed as text or json, not {:?}", format)),
+    }
+    Ok(())
+}
+
+/// Records the entry of a timer that stopped and checks it against the commits made while it ran;
+/// the timer file is saved before, so a failing check never loses the entry.
+fn finish(ctx: &Context, running: &RunningTimer, entry: TimeEntry) -> Result<()> {
+    if let Some(store) = &ctx.store {
+        store.save_time_entries(std::slice::from_ref(&entry))?;
+    }
+    let check = ctx.workload.check_timer(running, entry.end_time)?;
+
+    if let Some(format) = ctx.args.format.export() {
+        print!("{}", render("Timer", std::slice::from_ref(&entry), format)?);
+        return Ok(());
+    }
+    println!("Stopped {} after {:.2}h", describe(running), hours(entry.duration()));
+    print_check(running, &check);
+    Ok(())
+}
+
+fn print_check(running: &RunningTimer, check: &TimerCheck) {
+    println!("  {} commits on {} while the timer ran", check.on_project, running.project);
+    for (code, count) in &check.elsewhere {
+        println!("  {} {} commits on {} instead", "warning:".yellow(), count, code);
+    }
+    for (task, count) in &check.other_tasks {
+        let timed = running.task.as_deref().unwrap_or_default();
+        println!("  {} {} commits for {} rather than {}", "warning:".yellow(), count, task, timed);
+    }
+}
+
+fn describe(running: &RunningTimer) -> String {
+    match &running.task {
+        Some(task) => format!("{} ({})", running.project.bold(), task),
+        None => running.project.bold().to_string(),
+    }
+}
+
+fn find_project<'a>(ctx: &'a Context, name: &str) -> Result<&'a Project> {
+    ctx.workload
+        .select_projects(Some(name))
+        .into_iter()
+        .next()
+        .ok_or_else(|| eyre!("No project named or coded '{}' in {}", name, ctx.args.config.display()))
+}
+
+/// The current time to the second, which is all timesheets show.
+fn now() -> DateTime<Utc> {
+    Utc::now().trunc_subsecs(0)
+}
+
+fn load(ctx: &Context) -> Result<TimerState> {
+    let path = ctx.timer_path();
+    TimerState::load(&path).map_err(|e| eyre!("Failed to load {}: {}", path.display(), e))
+}
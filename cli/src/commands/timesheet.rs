@@ -0,0 +1,38 @@
+use super::hours::hours;
+use crate::args::Context;
+use color_eyre::eyre::Result;
+use timex_core::render::render;
+
+pub fn run(ctx: &Context) -> Result<()> {
+    let options = ctx.walk_options(None);
+    let mut entries = Vec::new();
+    for project in ctx.projects()? {
+        entries.extend(ctx.project_time_entries(project, &options)?);
+    }
+    entries.sort_by_key(|entry| entry.start_time);
+
+    if let Some(format) = ctx.args.format.export() {
+        print!("{}", render("Timesheet", &entries, format)?);
+        return Ok(());
+    }
+
+    for entry in entries {
+        let start = entry.local_start();
+        let end = entry.local_end();
+        let provenance = match entry.provenance.is_empty() {
+            true => String::new(),
+            false => format!("  ({})", entry.provenance),
+        };
+        println!(
+            "{}  {}-{}  {:<12} {:<12} {:>6.2}h{}",
+            start.format("%Y-%m-%d"),
+            start.format("%H:%M"),
+            end.format("%H:%M"),
+            entry.project,
+            entry.task.as_deref().unwrap_or("-"),
+            hours(entry.duration()),
+            provenance
+        );
+    }
+    Ok(())
+}
@@ -1,3 +1,103 @@
-fn main() {
-    println!("Hello, world!");
+mod args;
+mod commands;
+
+use args::{Context, GlobalArgs, Layout};
+use clap::{Parser, Subcommand};
+use std::path::PathBuf;
+
+/// Timesheets and work summaries from git history
+#[derive(Debug, Parser)]
+#[command(name = "timex", version, about)]
+struct Cli {
+    #[command(flatten)]
+    global: GlobalArgs,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// List your commits
+    Log {
+        /// Revspec to walk instead of every ref, e.g. `main` or `v1.0..HEAD`
+        #[arg(long)]
+        rev: Option<String>,
+    },
+    /// Show the diffs your commits introduced
+    Diff {
+        /// Revspec to walk instead of every ref, e.g. `main` or `v1.0..HEAD`
+        #[arg(long)]
+        rev: Option<String>,
+        /// Layout of diffs in HTML output
+        #[arg(long, value_enum, default_value_t = Layout::Unified)]
+        layout: Layout,
+    },
+    /// Total working hours per project, ticket and day
+    Hours,
+    /// Summarise your work on each project
+    Report {
+        /// Summarise without calling a language model
+        #[arg(long)]
+        offline: bool,
+        /// Layout of diffs in HTML output
+        #[arg(long, value_enum, default_value_t = Layout::Unified)]
+        layout: Layout,
+    },
+    /// Record commits made since the last sync in the local store
+    Sync {
+        /// Where the last processed tip of every ref is kept [default: timex-state.json next to the configuration]
+        #[arg(long)]
+        state: Option<PathBuf>,
+        /// Walk the full history again instead of only what is new
+        #[arg(long)]
+        full: bool,
+    },
+    /// List working sessions, inferred and manual
+    Timesheet,
+    /// Print the resolved configuration
+    Config,
+    /// Start timing work on a project
+    Start {
+        /// Name or code of the project
+        project: String,
+        /// Task code, e.g. a ticket ID
+        #[arg(long)]
+        task: Option<String>,
+    },
+    /// Stop the running timer and record its time
+    Stop,
+    /// Stop the running timer and start timing another project
+    Switch {
+        /// Name or code of the project
+        project: String,
+        /// Task code, e.g. a ticket ID
+        #[arg(long)]
+        task: Option<String>,
+    },
+    /// Show the running timer and the commits made while it ran
+    Status,
+}
+
+#[tokio::main]
+async fn main() -> color_eyre::Result<()> {
+    color_eyre::install()?;
+    dotenv::dotenv().ok();
+
+    let cli = Cli::parse();
+    let ctx = Context::load(cli.global)?;
+
+    match cli.command {
+        Command::Log { rev } => commands::log::run(&ctx, rev),
+        Command::Diff { rev, layout } => commands::diff::run(&ctx, rev, layout.into()),
+        Command::Hours => commands::hours::run(&ctx),
+        Command::Report { offline, layout } => commands::report::run(&ctx, offline, layout.into()).await,
+        Command::Sync { state, full } => commands::sync::run(&ctx, state, full),
+        Command::Timesheet => commands::timesheet::run(&ctx),
+        Command::Config => commands::config::run(&ctx),
+        Command::Start { project, task } => commands::timer::start(&ctx, &project, task),
+        Command::Stop => commands::timer::stop(&ctx),
+        Command::Switch { project, task } => commands::timer::switch(&ctx, &project, task),
+        Command::Status => commands::timer::status(&ctx),
+    }
 }
@@ -0,0 +1,195 @@
+use super::error::GitError;
+use gix::attrs::search::Outcome;
+use gix::attrs::StateRef;
+use gix::bstr::{BStr, BString, ByteSlice, ByteVec};
+use gix::index::entry::{Flags, Mode};
+use gix::ObjectId;
+use gix::worktree::stack::state::attributes::Source;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// What a repository's `.gitattributes` say about a changed file.
+///
+/// Marked files are listed in diffs without their contents, which are never loaded, and are
+/// counted separately from hand-written code in statistics and summaries.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileAttributes {
+    /// `linguist-generated`: the file is produced by a tool, e.g. a lock file or generated bindings.
+    #[serde(default)]
+    pub generated: bool,
+    /// `linguist-vendored`: the file is third-party code checked into the repository.
+    #[serde(default)]
+    pub vendored: bool,
+    /// `binary` or `-diff`: git does not show a textual diff for the file.
+    #[serde(default)]
+    pub binary: bool,
+}
+
+impl FileAttributes {
+    /// Whether any attribute is set, i.e. the file's contents are not diffed.
+    pub fn is_marked(&self) -> bool {
+        self.generated || self.vendored || self.binary
+    }
+
+    /// The names of the set attributes, e.g. `["generated", "vendored"]`.
+    pub fn labels(&self) -> Vec<&'static str> {
+        [
+            (self.generated, "generated"),
+            (self.vendored, "vendored"),
+            (self.binary, "binary"),
+        ]
+        .into_iter()
+        .filter_map(|(set, label)| set.then_some(label))
+        .collect()
+    }
+}
+
+/// Resolves [`FileAttributes`] from the `.gitattributes` files of one tree, plus the repository's
+/// `info/attributes` and the configured global attributes file.
+///
+/// Only the `.gitattributes` files in the directories of the paths asked about are read from the
+/// tree, so a commit costs a lookup per directory rather than a walk of the whole tree.
+pub(crate) struct AttributeLookup<'repo> {
+    repo: &'repo gix::Repository,
+    tree: gix::Tree<'repo>,
+    /// The `.gitattributes` blob of each directory looked at so far, if it has one.
+    files: BTreeMap<BString, Option<ObjectId>>,
+    stack: gix::AttributeStack<'repo>,
+    outcome: Outcome,
+}
+
+impl<'repo> AttributeLookup<'repo> {
+    /// Reads attributes as they are in `tree`, so that each commit is judged by its own rules
+    /// regardless of what is checked out.
+    pub fn for_tree(repo: &'repo gix::Repository, tree: &gix::Tree<'repo>) -> Result<Self, GitError> {
+        let mut files = BTreeMap::new();
+        files.insert(BString::default(), attributes_file(tree, "".into())?);
+        let stack = build_stack(repo, &files)?;
+        let outcome = stack.selected_attribute_matches(["linguist-generated", "linguist-vendored", "binary", "diff"]);
+        Ok(AttributeLookup {
+            repo,
+            tree: tree.clone(),
+            files,
+            stack,
+            outcome,
+        })
+    }
+
+    /// Reads the `.gitattributes` files of the directories above `path` not seen yet, and rebuilds
+    /// the stack if any of them exists.
+    fn discover(&mut self, path: &BStr) -> Result<(), GitError> {
+        let mut found = false;
+        let ends = path.iter().enumerate().filter(|(_, byte)| **byte == b'/').map(|(end, _)| end);
+        for dir in ends.map(|end| path[..end].as_bstr()) {
+            if !self.files.contains_key(dir) {
+                let id = attributes_file(&self.tree, dir)?;
+                found |= id.is_some();
+                self.files.insert(dir.to_owned(), id);
+            }
+        }
+        if found {
+            self.stack = build_stack(self.repo, &self.files)?;
+            self.outcome = self.stack.selected_attribute_matches([
+                "linguist-generated",
+                "linguist-vendored",
+                "binary",
+                "diff",
+            ]);
+        }
+        Ok(())
+    }
+
+    /// The attributes of the file at `path`; a path whose rules cannot be read has none.
+    pub fn get(&mut self, path: &BStr) -> FileAttributes {
+        let mut attributes = FileAttributes::default();
+        if self.discover(path).is_err() {
+            return attributes;
+        }
+        let Ok(platform) = self.stack.at_entry(path, None) else {
+            return attributes;
+        };
+        self.outcome.reset();
+        platform.matching_attributes(&mut self.outcome);
+
+        for matched in self.outcome.iter_selected() {
+            let state = matched.assignment.state;
+            match matched.assignment.name.as_str() {
+                "linguist-generated" => attributes.generated = is_true(state),
+                "linguist-vendored" => attributes.vendored = is_true(state),
+                "binary" => attributes.binary |= is_true(state),
+                "diff" => attributes.binary |= state.is_unset(),
+                _ => {}
+            }
+        }
+        attributes
+    }
+}
+
+/// The id of the `.gitattributes` blob in `dir` of `tree`, the root being the empty path.
+fn attributes_file(tree: &gix::Tree<'_>, dir: &BStr) -> Result<Option<ObjectId>, GitError> {
+    let components = dir.split_str("/").filter(|component| !component.is_empty());
+    let path = components.chain(Some(".gitattributes".as_bytes()));
+    let entry = tree.lookup_entry(path)?;
+    Ok(entry.filter(|entry| entry.mode().is_blob()).map(|entry| entry.object_id()))
+}
+
+/// An attribute stack over the repository's global attributes and `files`, which are mapped to
+/// their blobs through a minimal index that holds nothing else.
+fn build_stack<'repo>(
+    repo: &'repo gix::Repository,
+    files: &BTreeMap<BString, Option<ObjectId>>,
+) -> Result<gix::AttributeStack<'repo>, GitError> {
+    let mut index = gix::index::State::new(repo.object_hash());
+    for (dir, id) in files.iter().filter_map(|(dir, id)| Some((dir, (*id)?))) {
+        let mut path = dir.clone();
+        if !path.is_empty() {
+            path.push(b'/');
+        }
+        path.push_str(".gitattributes");
+        index.dangerously_push_entry(Default::default(), id, Flags::empty(), Mode::FILE, path.as_bstr());
+    }
+    index.sort_entries();
+    Ok(repo.attributes_only(&index, Source::IdMapping)?)
+}
+
+/// Linguist accepts `attr`, `attr=true` and `attr=1`, and `-attr` or `attr=false` to override.
+fn is_true(state: StateRef<'_>) -> bool {
+    match state {
+        StateRef::Set => true,
+        StateRef::Value(value) => value.as_bstr() != "false" && value.as_bstr() != "0",
+        StateRef::Unset | StateRef::Unspecified => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git::test_repo::TestRepo;
+
+    #[test]
+    fn test_attributes_come_from_the_tree() {
+        let test_repo = TestRepo::new();
+        test_repo.write(
+            ".gitattributes",
+            "*.lock linguist-generated\nthird_party/** linguist-vendored\n*.png binary\n*.svg -diff\n\
+             keep.lock linguist-generated=false\n",
+        );
+        test_repo.write("docs/.gitattributes", "*.md linguist-generated\n");
+        let head = test_repo.commit("src/main.rs", "fn main() {}\n", "attributes");
+        test_repo.write(".gitattributes", "");
+        let repo = test_repo.open();
+
+        let tree = repo.find_commit(gix::ObjectId::from_hex(head.as_bytes()).unwrap()).unwrap().tree().unwrap();
+        let mut lookup = AttributeLookup::for_tree(&repo, &tree).unwrap();
+        let mut labels = |path: &str| lookup.get(path.into()).labels();
+
+        assert_eq!(labels("Cargo.lock"), ["generated"]);
+        assert_eq!(labels("keep.lock"), Vec::<&str>::new());
+        assert_eq!(labels("third_party/zlib/inflate.c"), ["vendored"]);
+        assert_eq!(labels("assets/logo.png"), ["binary"]);
+        assert_eq!(labels("assets/icon.svg"), ["binary"]);
+        assert_eq!(labels("src/main.rs"), Vec::<&str>::new());
+        assert_eq!(labels("docs/api/index.md"), ["generated"]);
+        assert_eq!(labels("README.md"), Vec::<&str>::new());
+    }
+}
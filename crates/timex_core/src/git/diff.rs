@@ -1,39 +1,68 @@
-
-
+use super::attributes::{AttributeLookup, FileAttributes};
 use super::error::GitError;
-use gix::bstr::{BStr, BString, ByteSlice};
-use gix::diff::blob::pipeline::Mode;
-use gix::diff::blob::{DiffLineStats, Pipeline};
+use super::hunk::{build_hunks, Hunk, DEFAULT_CONTEXT_LINES};
+use super::identity::IdentityFilter;
+use super::paths::PathFilter;
+use super::GitCommit;
+use crate::pool::map_bounded;
 use gix::diff::Rewrites;
-use gix::open::permissions::Config;
-use gix::worktree::Stack;
-use std::io::Write;
 use colored::*;
 use std::fmt;
-use syntect::easy::HighlightLines;
-use syntect::highlighting::{ThemeSet, Style};
-use syntect::parsing::SyntaxSet;
-use syntect::util::{as_24_bit_terminal_escaped, LinesWithEndings};
-use lazy_static::lazy_static;
+use std::ops::Range;
+use serde::{Deserialize, Serialize};
+use chrono::{DateTime, Utc};
 
-#[derive(Debug)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct CommitDiff {
     pub old_commit: String,
     pub new_commit: String,
     pub changes: Vec<FileChange>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct FileChange {
     pub path: String,
     pub change_type: ChangeType,
     pub lines_added: usize,
     pub lines_removed: usize,
     pub source_path: Option<String>,
-    pub diff_lines: Vec<DiffLine>,
+    /// How similar the source and destination of a rename or copy are, from 0.0 to 1.0.
+    pub similarity: Option<f32>,
+    pub hunks: Vec<Hunk>,
+    /// What `.gitattributes` say about the file; marked files carry no hunks or line counts.
+    #[serde(default)]
+    pub attributes: FileAttributes,
+    /// Whether the file holds text; binary files and LFS pointers carry no hunks or line counts.
+    #[serde(default)]
+    pub content: FileContent,
 }
 
-#[derive(Debug)]
+/// What kind of content a changed file holds.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum FileContent {
+    #[default]
+    Text,
+    /// Binary data, by `.gitattributes` or because it contains NUL bytes; sizes are in bytes, and
+    /// `0` on the side where the file does not exist.
+    Binary { old_size: u64, new_size: u64 },
+    /// A Git LFS pointer to the object with `oid`, e.g. `sha256:4d7a…`, of `size` bytes.
+    Lfs { oid: String, size: u64 },
+}
+
+impl FileContent {
+    /// `text`, `binary` or `lfs`, as the variant is named when serialized.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            FileContent::Text => "text",
+            FileContent::Binary { .. } => "binary",
+            FileContent::Lfs { .. } => "lfs",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum ChangeType {
     Added,
     Deleted,
@@ -42,24 +71,111 @@ pub enum ChangeType {
     Copied,
 }
 
-#[derive(Debug)]
+impl ChangeType {
+    /// `added`, `deleted`, `modified`, `renamed` or `copied`, as the variant is named when serialized.
+    pub fn name(&self) -> &'static str {
+        match self {
+            ChangeType::Added => "added",
+            ChangeType::Deleted => "deleted",
+            ChangeType::Modified => "modified",
+            ChangeType::Renamed => "renamed",
+            ChangeType::Copied => "copied",
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
 pub struct DiffLine {
     pub line_type: DiffLineType,
     pub content: String,
-    pub syntax_highlighted: Option<String>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum DiffLineType {
     Added,
     Removed,
     Context,
 }
 
+/// Options controlling how file contents are diffed.
+#[derive(Debug, Clone)]
+pub struct DiffOptions {
+    /// Number of unchanged lines to keep around each change.
+    pub context_lines: u32,
+    /// The files to diff; the others are skipped before their contents are loaded.
+    pub paths: PathFilter,
+}
+
+impl Default for DiffOptions {
+    fn default() -> Self {
+        DiffOptions {
+            context_lines: DEFAULT_CONTEXT_LINES,
+            paths: PathFilter::default(),
+        }
+    }
+}
+
+impl FileChange {
+    /// Whether the change carries hunks and line counts, i.e. is a text file not marked by
+    /// `.gitattributes`.
+    pub fn is_diffed(&self) -> bool {
+        !self.attributes.is_marked() && self.content == FileContent::Text
+    }
+
+    /// A short description of why the change is not diffed, e.g. `generated` or `binary, 12 -> 20 bytes`.
+    pub fn opaque_label(&self) -> Option<String> {
+        match &self.content {
+            FileContent::Binary { old_size, new_size } => Some(format!("binary, {old_size} -> {new_size} bytes")),
+            FileContent::Lfs { oid, size } => Some(format!("LFS object {oid}, {size} bytes")),
+            FileContent::Text if self.attributes.is_marked() => Some(self.attributes.labels().join(", ")),
+            FileContent::Text => None,
+        }
+    }
+
+    /// All diff lines of this change, in hunk order.
+    pub fn diff_lines(&self) -> impl Iterator<Item = &DiffLine> {
+        self.hunks.iter().flat_map(|hunk| hunk.lines.iter())
+    }
+
+    /// Renders the change as a plain unified diff, without colours or syntax highlighting.
+    pub fn to_plain_text(&self) -> String {
+        let old_path = match self.change_type {
+            ChangeType::Added => "/dev/null".to_string(),
+            _ => format!("a/{}", self.source_path.as_deref().unwrap_or(&self.path)),
+        };
+        let new_path = match self.change_type {
+            ChangeType::Deleted => "/dev/null".to_string(),
+            _ => format!("b/{}", self.path),
+        };
+
+        let mut text = format!("--- {}\n+++ {}\n", old_path, new_path);
+        if let Some(label) = self.opaque_label() {
+            text.push_str(&format!("({label}, contents not diffed)\n"));
+        }
+        for hunk in &self.hunks {
+            text.push_str(&hunk.to_plain_text());
+        }
+        text
+    }
+}
+
+impl CommitDiff {
+    /// Renders the diff as plain text suitable for prompts and files, without ANSI escapes.
+    pub fn to_plain_text(&self) -> String {
+        let mut text = format!("diff {} -> {}\n", self.old_commit, self.new_commit);
+        for change in &self.changes {
+            text.push_str(&change.to_plain_text());
+        }
+        text
+    }
+}
+
 pub fn get_commit_diff(
     repo: &gix::Repository,
     old_commit: &str,
     new_commit: &str,
+    options: &DiffOptions,
 ) -> Result<CommitDiff, GitError> {
     let old_tree_id = repo
         .rev_parse_single(old_commit.as_bytes())?
@@ -70,36 +186,126 @@ pub fn get_commit_diff(
         .object()?
         .peel_to_tree()?;
 
+    diff_trees(repo, &old_tree_id, &new_tree_id, old_commit, new_commit, options)
+}
+
+/// Diffs `child` against `parent`, or against the empty tree if `parent` is `None`.
+pub fn get_parent_diff(
+    repo: &gix::Repository,
+    parent: Option<&ObjectId>,
+    child: &ObjectId,
+    options: &DiffOptions,
+) -> Result<CommitDiff, GitError> {
+    let new_tree = repo.find_commit(*child)?.tree()?;
+    let (old_tree, old_label) = match parent {
+        Some(parent) => (repo.find_commit(*parent)?.tree()?, parent.to_string()),
+        None => {
+            let empty = repo.empty_tree();
+            let label = empty.id.to_string();
+            (empty, label)
+        }
+    };
+    diff_trees(repo, &old_tree, &new_tree, &old_label, &child.to_string(), options)
+}
+
+/// Diffs a commit against its parents according to `policy`.
+///
+/// Root commits are diffed against the empty tree. Merges produce one diff per parent with
+/// [`MergePolicy::EachParent`], and a single diff holding only the files that differ from
+/// every parent with [`MergePolicy::Combined`]; that diff uses the first parent's hunks.
+pub fn get_edge_diffs(
+    repo: &gix::Repository,
+    edge: &CommitEdge,
+    policy: MergePolicy,
+    options: &DiffOptions,
+) -> Result<Vec<CommitDiff>, GitError> {
+    match (policy, edge.parents.as_slice()) {
+        (_, []) => Ok(vec![get_parent_diff(repo, None, &edge.child, options)?]),
+        (MergePolicy::FirstParent, [first, ..]) | (_, [first]) => {
+            Ok(vec![get_parent_diff(repo, Some(first), &edge.child, options)?])
+        }
+        (MergePolicy::EachParent, parents) => parents
+            .iter()
+            .map(|parent| get_parent_diff(repo, Some(parent), &edge.child, options))
+            .collect(),
+        (MergePolicy::Combined, [first, rest @ ..]) => {
+            let mut combined = get_parent_diff(repo, Some(first), &edge.child, options)?;
+            for parent in rest {
+                let other = get_parent_diff(repo, Some(parent), &edge.child, options)?;
+                let changed: HashSet<&str> = other.changes.iter().map(|c| c.path.as_str()).collect();
+                combined.changes.retain(|c| changed.contains(c.path.as_str()));
+            }
+            Ok(vec![combined])
+        }
+    }
+}
+
+/// [`get_edge_diffs`] for every edge, computed on up to `threads` workers that each open their
+/// own handle on `repo`. The diffs come back in edge order.
+pub fn get_edge_diffs_parallel(
+    repo: &gix::ThreadSafeRepository,
+    edges: &[CommitEdge],
+    policy: MergePolicy,
+    options: &DiffOptions,
+    threads: usize,
+) -> Result<Vec<CommitDiff>, GitError> {
+    let results = map_bounded(edges, threads, || repo.to_thread_local(), |repo, edge| {
+        get_edge_diffs(repo, edge, policy, options)
+    });
+
+    let mut diffs = Vec::with_capacity(edges.len());
+    for result in results {
+        diffs.extend(result?);
+    }
+    Ok(diffs)
+}
+
+fn diff_trees<'repo>(
+    repo: &'repo gix::Repository,
+    old_tree_id: &gix::Tree<'repo>,
+    new_tree_id: &gix::Tree<'repo>,
+    old_commit: &str,
+    new_commit: &str,
+    options: &DiffOptions,
+) -> Result<CommitDiff, GitError> {
     let mut changes = Vec::new();
     let mut diff_cache = repo.diff_resource_cache_for_tree_diff()?;
-  
+    // Deleted files are judged by the rules they were committed under, everything else by the new ones.
+    let mut old_lookup = AttributeLookup::for_tree(repo, old_tree_id)?;
+    let mut new_lookup = AttributeLookup::for_tree(repo, new_tree_id)?;
 
     old_tree_id
         .changes()?
         .options(|opts| {
             opts.track_path().track_rewrites(Some(Rewrites::default()));
         })
-        .for_each_to_obtain_tree(&new_tree_id, |change| {
+        .for_each_to_obtain_tree(new_tree_id, |change| {
+            if !options.paths.allows(change.location()) {
+                return Ok::<_, std::convert::Infallible>(Default::default());
+            }
+            let attributes = match change {
+                gix::object::tree::diff::Change::Deletion { location, .. } => old_lookup.get(location),
+                _ => new_lookup.get(change.location()),
+            };
+            if attributes.is_marked() {
+                changes.extend(marked_change(repo, &change, attributes));
+                return Ok(Default::default());
+            }
             match change {
                 gix::object::tree::diff::Change::Addition { entry_mode, location, id, .. } => {
                     if entry_mode.is_blob_or_symlink() {
                         if let Ok(obj) = id.object() {
-                            let content = String::from_utf8_lossy(&obj.data).to_string();
-                            let diff_lines = content
-                                .lines()
-                                .map(|line| DiffLine {
-                                    line_type: DiffLineType::Added,
-                                    content: line.to_string(),
-                                    syntax_highlighted: None,
-                                })
-                                .collect();
+                            let diff = whole_file_diff(&obj.data, ChangeType::Added);
                             changes.push(FileChange {
                                 path: location.to_string(),
                                 change_type: ChangeType::Added,
-                                lines_added: count_lines(&obj.data),
+                                lines_added: diff.insertions,
                                 lines_removed: 0,
                                 source_path: None,
-                                diff_lines,
+                                similarity: None,
+                                hunks: diff.hunks,
+                                attributes: FileAttributes::default(),
+                                content: diff.content,
                             });
                         }
                     }
@@ -107,91 +313,70 @@ pub fn get_commit_diff(
                 gix::object::tree::diff::Change::Deletion { entry_mode, location, id, .. } => {
                     if entry_mode.is_blob_or_symlink() {
                         if let Ok(obj) = id.object() {
-                            let content = String::from_utf8_lossy(&obj.data).to_string();
-                            let diff_lines = content
-                                .lines()
-                                .map(|line| DiffLine {
-                                    line_type: DiffLineType::Removed,
-                                    content: line.to_string(),
-                                    syntax_highlighted: None,
-                                })
-                                .collect();
+                            let diff = whole_file_diff(&obj.data, ChangeType::Deleted);
                             changes.push(FileChange {
                                 path: location.to_string(),
                                 change_type: ChangeType::Deleted,
                                 lines_added: 0,
-                                lines_removed: count_lines(&obj.data),
+                                lines_removed: diff.removals,
                                 source_path: None,
-                                diff_lines,
+                                similarity: None,
+                                hunks: diff.hunks,
+                                attributes: FileAttributes::default(),
+                                content: diff.content,
                             });
                         }
                     }
                 }
-                gix::object::tree::diff::Change::Modification { entry_mode, location, id, previous_id, .. } => {
+                gix::object::tree::diff::Change::Modification { entry_mode, location, .. } => {
                     if entry_mode.is_blob() {
-                        if let Ok(cache) = change.diff(&mut diff_cache).map(|p| p.resource_cache) {
-                            if let Ok(prep) = cache.prepare_diff() {
-                                let counts = gix::diff::blob::diff(
-                                    gix::diff::blob::Algorithm::Myers,
-                                    &prep.interned_input(),
-                                    gix::diff::blob::sink::Counter::default(),
-                                );
-
-                                // Get the raw content for both versions
-                                if let (Ok(old_obj), Ok(new_obj)) = (previous_id.object(), id.object()) {
-                                    let old_content = String::from_utf8_lossy(&old_obj.data);
-                                    let new_content = String::from_utf8_lossy(&new_obj.data);
-                                    
-                                    let mut diff_lines: Vec<DiffLine> = Vec::new();
-                                    
-                                    // Add removed lines
-                                    for line in old_content.lines() {
-                                        let syntax_highlighted = match syntax_highlight_content(line, &location) {
-                                            Ok(highlighted) => Some(highlighted),
-                                            Err(_) => None,
-                                        };
-                                        diff_lines.push(DiffLine {
-                                            line_type: DiffLineType::Removed,
-                                            content: line.to_string(),
-                                            syntax_highlighted,
-                                        });
-                                    }
-                                    
-                                    // Add added lines with syntax highlighting
-                                    for line in new_content.lines() {
-                                        let syntax_highlighted = match syntax_highlight_content(line, &location) {
-                                            Ok(highlighted) => Some(highlighted),
-                                            Err(_) => None,
-                                        };
-                                        diff_lines.push(DiffLine {
-                                            line_type: DiffLineType::Added,
-                                            content: line.to_string(),
-                                            syntax_highlighted,
-                                        });
-                                    }
-
-                                    changes.push(FileChange {
-                                        path: location.to_string(),
-                                        change_type: ChangeType::Modified,
-                                        lines_added: counts.insertions as usize,
-                                        lines_removed: counts.removals as usize,
-                                        source_path: None,
-                                        diff_lines,
-                                    });
-                                }
+                        if let Ok(platform) = change.diff(&mut diff_cache) {
+                            if let Some(diff) = line_diff(platform.resource_cache, options.context_lines) {
+                                changes.push(FileChange {
+                                    path: location.to_string(),
+                                    change_type: ChangeType::Modified,
+                                    lines_added: diff.insertions,
+                                    lines_removed: diff.removals,
+                                    source_path: None,
+                                    similarity: None,
+                                    hunks: diff.hunks,
+                                    attributes: FileAttributes::default(),
+                                    content: diff.content,
+                                });
                             }
                         }
                     }
                 }
-                gix::object::tree::diff::Change::Rewrite { location, source_location, copy, diff, .. } => {
-                    let diff_lines = Vec::new(); // TODO: Implement proper diff lines for rewrites
+                gix::object::tree::diff::Change::Rewrite { location, source_location, entry_mode, copy, diff, .. } => {
+                    // Exact renames carry no line stats as their content is identical.
+                    let similarity = diff.map(|d| d.similarity).unwrap_or(1.0);
+                    let line_diff = if entry_mode.is_blob() && diff.is_some() {
+                        change
+                            .diff(&mut diff_cache)
+                            .ok()
+                            .and_then(|platform| line_diff(platform.resource_cache, options.context_lines))
+                    } else {
+                        None
+                    };
+                    let (lines_added, lines_removed, hunks, content) = match line_diff {
+                        Some(d) => (d.insertions, d.removals, d.hunks, d.content),
+                        None => (
+                            diff.map(|d| d.insertions as usize).unwrap_or(0),
+                            diff.map(|d| d.removals as usize).unwrap_or(0),
+                            Vec::new(),
+                            FileContent::Text,
+                        ),
+                    };
                     changes.push(FileChange {
                         path: location.to_string(),
                         change_type: if copy { ChangeType::Copied } else { ChangeType::Renamed },
-                        lines_added: diff.map(|d| d.insertions as usize).unwrap_or(0),
-                        lines_removed: diff.map(|d| d.removals as usize).unwrap_or(0),
+                        lines_added,
+                        lines_removed,
                         source_path: Some(source_location.to_string()),
-                        diff_lines,
+                        similarity: Some(similarity),
+                        hunks,
+                        attributes: FileAttributes::default(),
+                        content,
                     });
                 }
             }
@@ -205,6 +390,192 @@ pub fn get_commit_diff(
     })
 }
 
+/// A change to a file marked by `.gitattributes`, described from the tree entries and object
+/// headers alone so that its contents are never loaded.
+fn marked_change(
+    repo: &gix::Repository,
+    change: &gix::object::tree::diff::Change<'_, '_, '_>,
+    attributes: FileAttributes,
+) -> Option<FileChange> {
+    use gix::object::tree::diff::Change;
+
+    if !change.entry_mode().is_blob_or_symlink() {
+        return None;
+    }
+    let (change_type, source_path, similarity, old_id, new_id) = match change {
+        Change::Addition { id, .. } => (ChangeType::Added, None, None, None, Some(id)),
+        Change::Deletion { id, .. } => (ChangeType::Deleted, None, None, Some(id), None),
+        Change::Modification { previous_id, id, .. } => (ChangeType::Modified, None, None, Some(previous_id), Some(id)),
+        Change::Rewrite { source_location, source_id, id, copy, diff, .. } => (
+            if *copy { ChangeType::Copied } else { ChangeType::Renamed },
+            Some(source_location.to_string()),
+            Some(diff.map_or(1.0, |d| d.similarity)),
+            Some(source_id),
+            Some(id),
+        ),
+    };
+    let size = |id: Option<&gix::Id<'_>>| id.and_then(|id| repo.find_header(id.detach()).ok()).map_or(0, |h| h.size());
+    let content = if attributes.binary {
+        FileContent::Binary {
+            old_size: size(old_id),
+            new_size: size(new_id),
+        }
+    } else {
+        FileContent::Text
+    };
+    Some(FileChange {
+        path: change.location().to_string(),
+        change_type,
+        lines_added: 0,
+        lines_removed: 0,
+        source_path,
+        similarity,
+        hunks: Vec::new(),
+        attributes,
+        content,
+    })
+}
+
+/// The outcome of diffing the two resources currently loaded into a diff cache.
+struct LineDiff {
+    insertions: usize,
+    removals: usize,
+    hunks: Vec<Hunk>,
+    content: FileContent,
+}
+
+impl LineDiff {
+    /// The outcome for content that has no lines to diff.
+    fn opaque(content: FileContent) -> Self {
+        LineDiff {
+            insertions: 0,
+            removals: 0,
+            hunks: Vec::new(),
+            content,
+        }
+    }
+}
+
+/// Git's heuristic: a file is binary if its first 8000 bytes contain a NUL byte.
+fn is_binary(data: &[u8]) -> bool {
+    data[..data.len().min(8000)].contains(&0)
+}
+
+/// Parses a Git LFS pointer file, which is at most 1024 bytes of `key value` lines starting with
+/// the spec version, e.g. `oid sha256:4d7a…` and `size 12345`.
+fn lfs_pointer(data: &[u8]) -> Option<FileContent> {
+    if data.len() > 1024 {
+        return None;
+    }
+    let text = std::str::from_utf8(data).ok()?;
+    let mut lines = text.lines();
+    if !lines.next()?.starts_with("version https://git-lfs.github.com/spec/") {
+        return None;
+    }
+    let (mut oid, mut size) = (None, None);
+    for line in lines {
+        match line.split_once(' ')? {
+            ("oid", value) => oid = Some(value.to_string()),
+            ("size", value) => size = Some(value.parse().ok()?),
+            _ => {}
+        }
+    }
+    Some(FileContent::Lfs { oid: oid?, size: size? })
+}
+
+/// Treats all of `data` as added or deleted, according to `change_type`.
+fn whole_file_diff(data: &[u8], change_type: ChangeType) -> LineDiff {
+    if let Some(pointer) = lfs_pointer(data) {
+        return LineDiff::opaque(pointer);
+    }
+    if is_binary(data) {
+        let size = data.len() as u64;
+        let (old_size, new_size) = match change_type {
+            ChangeType::Deleted => (size, 0),
+            _ => (0, size),
+        };
+        return LineDiff::opaque(FileContent::Binary { old_size, new_size });
+    }
+
+    let text = String::from_utf8_lossy(data);
+    let lines: Vec<&str> = text.lines().collect();
+    let all = 0..lines.len() as u32;
+    let (ranges, old, new, insertions, removals) = match change_type {
+        ChangeType::Deleted => ((all, 0..0), &lines[..], &[][..], 0, count_lines(data)),
+        _ => ((0..0, all), &[][..], &lines[..], count_lines(data), 0),
+    };
+    LineDiff {
+        insertions,
+        removals,
+        hunks: build_hunks(old, new, &[ranges], 0),
+        content: FileContent::Text,
+    }
+}
+
+/// Runs a line diff over the resources prepared in `cache` and groups the result into hunks.
+///
+/// Binary resources and LFS pointers yield no lines. Returns `None` if the resources can't be
+/// diffed, e.g. because the cache failed to load them.
+fn line_diff(cache: &mut gix::diff::blob::Platform, context_lines: u32) -> Option<LineDiff> {
+    use gix::diff::blob::platform::{prepare_diff::Operation, resource::Data};
+
+    let prep = cache.prepare_diff().ok()?;
+    // The diff pipeline applies the NUL heuristic, `binary`/`-diff` and the big-file threshold.
+    if prep.operation == Operation::SourceOrDestinationIsBinary {
+        let size = |data: Data<'_>| match data {
+            Data::Buffer(buffer) => buffer.len() as u64,
+            Data::Binary { size } => size,
+            Data::Missing => 0,
+        };
+        return Some(LineDiff::opaque(FileContent::Binary {
+            old_size: size(prep.old.data),
+            new_size: size(prep.new.data),
+        }));
+    }
+    if let Some(pointer) = prep.new.data.as_slice().and_then(lfs_pointer) {
+        return Some(LineDiff::opaque(pointer));
+    }
+    let input = prep.interned_input();
+
+    let mut ranges: Vec<(Range<u32>, Range<u32>)> = Vec::new();
+    let counts = gix::diff::blob::diff(
+        gix::diff::blob::Algorithm::Myers,
+        &input,
+        gix::diff::blob::sink::Counter::new(|before: Range<u32>, after: Range<u32>| {
+            ranges.push((before, after));
+        }),
+    );
+
+    let old_content: Vec<String> = input
+        .before
+        .iter()
+        .map(|token| token_to_line(input.interner[*token]))
+        .collect();
+    let new_content: Vec<String> = input
+        .after
+        .iter()
+        .map(|token| token_to_line(input.interner[*token]))
+        .collect();
+    let old_lines: Vec<&str> = old_content.iter().map(String::as_str).collect();
+    let new_lines: Vec<&str> = new_content.iter().map(String::as_str).collect();
+
+    let hunks = build_hunks(&old_lines, &new_lines, &ranges, context_lines);
+
+    Some(LineDiff {
+        insertions: counts.insertions as usize,
+        removals: counts.removals as usize,
+        hunks,
+        content: FileContent::Text,
+    })
+}
+
+/// Converts an interned line, which still carries its terminator, into display text.
+fn token_to_line(line: &[u8]) -> String {
+    String::from_utf8_lossy(line)
+        .trim_end_matches(['\n', '\r'])
+        .to_string()
+}
+
 fn count_lines(data: &[u8]) -> usize {
     data.iter().filter(|&&b| b == b'\n').count()
 }
@@ -213,19 +584,83 @@ fn count_lines(data: &[u8]) -> usize {
 
 
 
-use gix::{Repository, Reference};
-use std::collections::{HashSet, VecDeque};
-use gix::prelude::ObjectIdExt;
+use gix::Repository;
+use std::collections::{BTreeMap, BinaryHeap, HashSet};
 use gix::hash::ObjectId;
 
 
+/// How merge commits are diffed when walking parent edges.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MergePolicy {
+    /// Diff merges against their first parent only, like `git log --first-parent -p`.
+    #[default]
+    FirstParent,
+    /// Diff merges against every parent separately, like `git log -m -p`.
+    EachParent,
+    /// Keep only the files a merge changed relative to all of its parents, like `git log -c`.
+    Combined,
+}
+
+/// A commit together with the parents it has in the commit graph.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommitEdge {
+    pub child: ObjectId,
+    /// Parents in commit order; empty for root commits.
+    pub parents: Vec<ObjectId>,
+}
+
 pub trait Walker {
+    /// The repository the walk reads commits from
+    fn repo(&self) -> &Repository;
+
     /// Visit each commit in the walk
     fn visit<F>(&mut self, f: F) -> Result<(), GitError>
     where
         F: FnMut(&ObjectId) -> Result<(), GitError>;
 
+    /// Visit each commit in the walk together with its parents
+    fn edges<F>(&mut self, mut f: F) -> Result<(), GitError>
+    where
+        F: FnMut(&CommitEdge) -> Result<(), GitError>
+    {
+        let repo = self.repo().clone();
+        self.visit(|child| {
+            let commit = repo.find_commit(*child)?;
+            let parents = commit.parent_ids().map(|id| id.detach()).collect();
+            f(&CommitEdge { child: *child, parents })
+        })
+    }
+
+    /// Get (parent, child) pairs for each commit in the walk, selecting parents by `policy`.
+    ///
+    /// Root commits are reported once with a `None` parent, standing for the empty tree.
+    /// Combined diffs need all parents at once, so [`MergePolicy::Combined`] yields every
+    /// parent here; use [`Walker::edges`] with [`get_edge_diffs`] to get combined diffs.
+    fn parent_pairs<F>(&mut self, policy: MergePolicy, mut f: F) -> Result<(), GitError>
+    where
+        F: FnMut(Option<&ObjectId>, &ObjectId) -> Result<(), GitError>
+    {
+        self.edges(|edge| {
+            let parents = match policy {
+                MergePolicy::FirstParent => &edge.parents[..edge.parents.len().min(1)],
+                MergePolicy::EachParent | MergePolicy::Combined => &edge.parents[..],
+            };
+            if parents.is_empty() {
+                return f(None, &edge.child);
+            }
+            for parent in parents {
+                f(Some(parent), &edge.child)?;
+            }
+            Ok(())
+        })
+    }
+
     /// Get pairs of adjacent commits in the walk
+    ///
+    /// Adjacent commits in a breadth-first walk over all refs are frequently unrelated, so
+    /// diffing them attributes changes to the wrong commit.
+    #[deprecated(note = "adjacent commits are not necessarily related; use `parent_pairs` or `edges`")]
     fn adjacent_pairs<F>(&mut self, mut f: F) -> Result<(), GitError>
     where
         F: FnMut(&ObjectId, &ObjectId) -> Result<(), GitError>
@@ -235,7 +670,7 @@ pub trait Walker {
             if let Some(prev) = previous.as_ref() {
                 f(prev, current)?;
             }
-            previous = Some(current.clone());
+            previous = Some(*current);
             Ok(())
         })
     }
@@ -243,68 +678,244 @@ pub trait Walker {
 
 
 
+/// Restrictions applied to the commits yielded by a walk.
+///
+/// Commits that are filtered out are still traversed, so their ancestors are reached.
+#[derive(Debug, Clone, Default)]
+pub struct WalkOptions {
+    /// Only yield commits authored or committed by this identity.
+    pub identity: Option<IdentityFilter>,
+    /// Only yield commits authored at or after this time, so rebased or cherry-picked work counts
+    /// on the day it was written rather than the day it was replayed.
+    pub since: Option<DateTime<Utc>>,
+    /// Only yield commits authored at or before this time.
+    pub until: Option<DateTime<Utc>>,
+    /// Walk the commits selected by this revspec, e.g. `main`, `A..B`, `A...B` or `@{yesterday}`,
+    /// instead of everything reachable from any reference.
+    pub revspec: Option<String>,
+    /// Never yield commits reachable from these, e.g. tips already processed by an earlier run.
+    pub hidden: Vec<ObjectId>,
+}
+
+impl WalkOptions {
+    pub fn with_identity(mut self, filter: IdentityFilter) -> Self {
+        self.identity = Some(filter);
+        self
+    }
+
+    pub fn with_since(mut self, since: DateTime<Utc>) -> Self {
+        self.since = Some(since);
+        self
+    }
+
+    pub fn with_until(mut self, until: DateTime<Utc>) -> Self {
+        self.until = Some(until);
+        self
+    }
+
+    pub fn with_revspec(mut self, revspec: impl Into<String>) -> Self {
+        self.revspec = Some(revspec.into());
+        self
+    }
+
+    pub fn with_hidden(mut self, hidden: impl IntoIterator<Item = ObjectId>) -> Self {
+        self.hidden.extend(hidden);
+        self
+    }
+
+    /// Whether `commit` passes every configured filter.
+    pub fn matches(&self, commit: &gix::Commit<'_>) -> Result<bool, GitError> {
+        if !self.in_window(commit.author()?.time.seconds) {
+            return Ok(false);
+        }
+        match &self.identity {
+            Some(filter) => filter.matches(commit),
+            None => Ok(true),
+        }
+    }
+
+    /// Whether a commit read earlier, e.g. from the store, lies between `since` and `until`. The
+    /// identity is not checked.
+    pub fn includes(&self, commit: &GitCommit) -> bool {
+        self.in_window(commit.author_date.timestamp())
+    }
+
+    fn in_window(&self, seconds: i64) -> bool {
+        self.since.is_none_or(|since| seconds >= since.timestamp())
+            && self.until.is_none_or(|until| seconds <= until.timestamp())
+    }
+}
+
+/// Walks commits newest first by commit time, like `git log`.
+///
+/// With [`WalkOptions::since`] set, the walk stops once every queued commit was committed before the
+/// cutoff; commits are authored before they are committed, so none authored since is missed. As with
+/// `git log --since`, commits with skewed clocks below an older commit can be missed.
+///
+/// Hidden tips are walked alongside the visible ones and mark their ancestry as they go, so the walk
+/// ends where the histories meet instead of first traversing everything behind the hidden tips.
 pub struct CommitTreeIterator<'a> {
     repo: &'a Repository,
-    queue: VecDeque<ObjectId>,
+    /// Commits to visit by commit time; at equal times hidden commits come first, so that commits
+    /// made within the same second are still recognised as hidden before they are yielded.
+    queue: BinaryHeap<(i64, bool, ObjectId)>,
     visited: HashSet<ObjectId>,
+    /// Commits known to be reachable from a hidden tip.
+    hidden: HashSet<ObjectId>,
+    options: WalkOptions,
 }
 
 impl<'a> CommitTreeIterator<'a> {
     pub fn new(repo: &'a Repository) -> Result<Self, GitError> {
-        let mut queue = VecDeque::new();
-        let mut visited = HashSet::new();
-        
-        // Get all branches
-        let refs = repo.references()?;
-        
-        let ref_iter = refs.all()?;
+        Self::with_options(repo, WalkOptions::default())
+    }
 
-        for reference in ref_iter.into_iter() {
-            let reference = reference?;
-                if let Ok(commit_id) = reference.id().detach().try_into() {
-                    queue.push_back(commit_id);
-                }
-            
+    pub fn with_options(repo: &'a Repository, options: WalkOptions) -> Result<Self, GitError> {
+        let (tips, mut hidden) = match &options.revspec {
+            Some(revspec) => resolve_revspec(repo, revspec)?,
+            None => (reference_tips(repo)?, Vec::new()),
+        };
+        hidden.extend(options.hidden.iter().copied());
+
+        let mut iter = CommitTreeIterator {
+            repo,
+            queue: BinaryHeap::new(),
+            visited: HashSet::new(),
+            hidden: hidden.iter().copied().collect(),
+            options,
+        };
+        for tip in tips.into_iter().chain(hidden) {
+            iter.enqueue(tip)?;
         }
+        Ok(iter)
+    }
 
-      
+    fn enqueue(&mut self, id: ObjectId) -> Result<(), GitError> {
+        if !self.visited.contains(&id) {
+            let time = self.repo.find_commit(id)?.time()?.seconds;
+            self.queue.push((time, self.hidden.contains(&id), id));
+        }
+        Ok(())
+    }
 
-        Ok(CommitTreeIterator {
-            repo,
-            queue,
-            visited,
-        })
+    fn is_past_cutoff(&self) -> bool {
+        match (self.options.since, self.queue.peek()) {
+            (Some(since), Some((time, _, _))) => *time < since.timestamp(),
+            _ => false,
+        }
+    }
+
+    /// Whether only hidden commits are left to walk, so nothing else can be yielded.
+    fn only_hidden_queued(&self) -> bool {
+        self.queue.iter().all(|(_, _, id)| self.hidden.contains(id))
+    }
+}
+
+/// Every reference that points to a commit, by full name, peeling tags.
+pub fn named_reference_tips(repo: &Repository) -> Result<BTreeMap<String, ObjectId>, GitError> {
+    let mut tips = BTreeMap::new();
+    for reference in repo.references()?.all()? {
+        let mut reference = reference?;
+        if let Ok(commit) = reference.peel_to_commit() {
+            tips.insert(reference.name().as_bstr().to_string(), commit.id);
+        }
+    }
+    Ok(tips)
+}
+
+/// Whether `ancestor` is reachable from `descendant`, counting a commit as its own ancestor.
+pub fn is_ancestor(repo: &Repository, ancestor: ObjectId, descendant: ObjectId) -> Result<bool, GitError> {
+    use gix::repository::merge_base::Error;
+
+    match repo.merge_base(ancestor, descendant) {
+        Ok(base) => Ok(base.detach() == ancestor),
+        Err(Error::NotFound { .. }) => Ok(false),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Every commit that a reference points to, peeling tags and skipping non-commit references.
+fn reference_tips(repo: &Repository) -> Result<Vec<ObjectId>, GitError> {
+    Ok(named_reference_tips(repo)?.into_values().collect())
+}
+
+/// Splits a revspec into the commits to walk from and the commits whose ancestry is excluded.
+fn resolve_revspec(repo: &Repository, revspec: &str) -> Result<(Vec<ObjectId>, Vec<ObjectId>), GitError> {
+    use gix::revision::plumbing::Spec;
+
+    let peel = |id: ObjectId| -> Result<ObjectId, GitError> {
+        Ok(repo.find_object(id)?.peel_to_commit()?.id)
+    };
+    let parents = |id: ObjectId| -> Result<Vec<ObjectId>, GitError> {
+        Ok(repo.find_commit(peel(id)?)?.parent_ids().map(|id| id.detach()).collect())
+    };
+
+    // gix reads relative reflog dates like `@{2 days ago}` but not git's `@{yesterday}`.
+    let revspec = revspec.replace("@{yesterday}", "@{1 day ago}");
+    Ok(match repo.rev_parse(revspec.as_str())?.detach() {
+        Spec::Include(id) => (vec![peel(id)?], Vec::new()),
+        Spec::Exclude(id) => (Vec::new(), vec![peel(id)?]),
+        Spec::Range { from, to } => (vec![peel(to)?], vec![peel(from)?]),
+        Spec::Merge { theirs, ours } => {
+            let (theirs, ours) = (peel(theirs)?, peel(ours)?);
+            let theirs_ancestors = ancestors(repo, vec![theirs])?;
+            let ours_ancestors = ancestors(repo, vec![ours])?;
+            let common = theirs_ancestors.intersection(&ours_ancestors).copied().collect();
+            (vec![theirs, ours], common)
+        }
+        Spec::IncludeOnlyParents(id) => (parents(id)?, Vec::new()),
+        Spec::ExcludeParents(id) => (vec![peel(id)?], parents(id)?),
+    })
+}
+
+/// All commits reachable from `tips`, including the tips themselves.
+fn ancestors(repo: &Repository, tips: Vec<ObjectId>) -> Result<HashSet<ObjectId>, GitError> {
+    let mut seen = HashSet::new();
+    let mut stack = tips;
+    while let Some(id) = stack.pop() {
+        if seen.insert(id) {
+            stack.extend(repo.find_commit(id)?.parent_ids().map(|id| id.detach()));
+        }
     }
+    Ok(seen)
 }
 
 impl<'a> Iterator for CommitTreeIterator<'a> {
     type Item = Result<ObjectId, GitError>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        while let Some(commit_id) = self.queue.pop_front() {
+        while !self.is_past_cutoff() {
+            let (_, _, commit_id) = self.queue.pop()?;
+
             // Skip if already visited
-            if !self.visited.insert(commit_id.clone()) {
+            if !self.visited.insert(commit_id) {
                 continue;
             }
 
             // Get commit's parents and add them to queue
-            match self.repo.find_commit(commit_id.clone()) {
-                Ok(commit) => {
-                    // Add parents to queue
-                    for parent_id in commit.parent_ids() {
-                        match parent_id.object() {
-                            Ok(obj) => match obj.try_into_commit() {
-                                Ok(parent) => {
-                                    self.queue.push_back(parent.id().detach());
-                                }
-                                Err(e) => return Some(Err(e.into())),
-                            },
-                            Err(e) => return Some(Err(e.into())),
-                        }
-                    }
-                    return Some(Ok(commit_id));
-                }
+            let commit = match self.repo.find_commit(commit_id) {
+                Ok(commit) => commit,
                 Err(e) => return Some(Err(e.into())),
+            };
+            let is_hidden = self.hidden.contains(&commit_id);
+            for parent_id in commit.parent_ids() {
+                if is_hidden {
+                    self.hidden.insert(parent_id.detach());
+                }
+                if let Err(e) = self.enqueue(parent_id.detach()) {
+                    return Some(Err(e));
+                }
+            }
+            if is_hidden {
+                if self.only_hidden_queued() {
+                    return None;
+                }
+                continue;
+            }
+            match self.options.matches(&commit) {
+                Ok(true) => return Some(Ok(commit_id)),
+                Ok(false) => continue,
+                Err(e) => return Some(Err(e)),
             }
         }
         None
@@ -314,6 +925,10 @@ impl<'a> Iterator for CommitTreeIterator<'a> {
 
 // Implement for CommitTreeIterator
 impl<'a> Walker for CommitTreeIterator<'a> {
+    fn repo(&self) -> &Repository {
+        self.repo
+    }
+
     fn visit<F>(&mut self, mut f: F) -> Result<(), GitError>
     where
         F: FnMut(&ObjectId) -> Result<(), GitError>
@@ -337,9 +952,9 @@ impl fmt::Display for CommitDiff {
     }
 }
 
-impl fmt::Display for FileChange {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        // Header with file path and stats
+impl FileChange {
+    /// Writes the coloured one-line summary shown above the file's hunks.
+    pub(crate) fn write_header(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let change_symbol = match self.change_type {
             ChangeType::Added => "+".green(),
             ChangeType::Deleted => "-".red(),
@@ -354,16 +969,30 @@ impl fmt::Display for FileChange {
         if let Some(source) = &self.source_path {
             write!(f, "(from: {}) ", source.bright_black())?;
         }
+
+        if let Some(similarity) = self.similarity {
+            write!(f, "({} {:.0}%) ", "similarity:".bright_black(), similarity * 100.0)?;
+        }
+
+        if let Some(label) = self.opaque_label() {
+            return writeln!(f, "({})", label.bright_black());
+        }
         
         writeln!(f, "({} +{}, -{})", 
             "changes:".bright_black(),
             self.lines_added.to_string().green(),
             self.lines_removed.to_string().red()
-        )?;
+        )
+    }
+}
+
+impl fmt::Display for FileChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.write_header(f)?;
 
-        // Print diff lines
-        for line in &self.diff_lines {
-            writeln!(f, "{}", line)?;
+        // Print hunks
+        for hunk in &self.hunks {
+            write!(f, "{}", hunk)?;
         }
         
         writeln!(f) // Extra newline between files
@@ -378,41 +1007,381 @@ impl fmt::Display for DiffLine {
             DiffLineType::Context => " ".normal(),
         };
         
-        // Use syntax highlighted content if available, otherwise use plain content
-        let content = if let Some(highlighted) = &self.syntax_highlighted {
-            highlighted.to_string()
-        } else {
-            match self.line_type {
-                DiffLineType::Added => self.content.green(),
-                DiffLineType::Removed => self.content.red(),
-                DiffLineType::Context => self.content.normal(),
-            }.to_string()
+        let content = match self.line_type {
+            DiffLineType::Added => self.content.green(),
+            DiffLineType::Removed => self.content.red(),
+            DiffLineType::Context => self.content.normal(),
         };
-        
+
         write!(f, "{} {}", prefix, content)
     }
 }
 
-fn syntax_highlight_content(content: &str, file_path: &BStr) -> Result<String, GitError> {
-    lazy_static! {
-        static ref PS: SyntaxSet = SyntaxSet::load_defaults_newlines();
-        static ref TS: ThemeSet = ThemeSet::load_defaults();
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git::identity::{Identity, SignatureRole};
+    use crate::git::test_repo::TestRepo;
+    use chrono::TimeZone;
+
+    /// Builds `main: a - b - m` where `m` merges a `feature` branch with commit `f`.
+    fn merge_repo() -> (TestRepo, [ObjectId; 4]) {
+        let repo = TestRepo::new();
+        let a = repo.commit("shared.txt", "one\n", "a");
+        repo.git(&["checkout", "-q", "-b", "feature"]);
+        let f = repo.commit("feature.txt", "feature\n", "f");
+        repo.git(&["checkout", "-q", "main"]);
+        let b = repo.commit("main.txt", "main\n", "b");
+        repo.git(&["merge", "-q", "--no-ff", "-m", "m", "feature"]);
+        let m = repo.git(&["rev-parse", "HEAD"]);
+        let ids = [a, b, f, m].map(|id| ObjectId::from_hex(id.as_bytes()).unwrap());
+        (repo, ids)
+    }
+
+    #[test]
+    fn test_parent_pairs_follow_parents() {
+        let (repo, [a, b, f, m]) = merge_repo();
+        let repo = repo.open();
+
+        let mut pairs = Vec::new();
+        CommitTreeIterator::new(&repo)
+            .unwrap()
+            .parent_pairs(MergePolicy::FirstParent, |parent, child| {
+                pairs.push((parent.copied(), *child));
+                Ok(())
+            })
+            .unwrap();
+        pairs.sort();
+
+        let mut expected = vec![(None, a), (Some(a), b), (Some(a), f), (Some(b), m)];
+        expected.sort();
+        assert_eq!(pairs, expected);
+    }
+
+    #[test]
+    fn test_change_type_names_match_serde() {
+        use ChangeType::*;
+        for change_type in [Added, Deleted, Modified, Renamed, Copied] {
+            assert_eq!(serde_json::to_value(change_type).unwrap(), change_type.name());
+        }
+    }
+
+    #[test]
+    fn test_merge_policies() {
+        let (test_repo, [_, b, f, m]) = merge_repo();
+        let repo = test_repo.open();
+        let edge = CommitEdge { child: m, parents: vec![b, f] };
+        let options = DiffOptions::default();
+        let paths = |diffs: &[CommitDiff]| -> Vec<Vec<String>> {
+            diffs.iter().map(|d| d.changes.iter().map(|c| c.path.clone()).collect()).collect()
+        };
+
+        let first = get_edge_diffs(&repo, &edge, MergePolicy::FirstParent, &options).unwrap();
+        assert_eq!(paths(&first), vec![vec!["feature.txt"]]);
+
+        let each = get_edge_diffs(&repo, &edge, MergePolicy::EachParent, &options).unwrap();
+        assert_eq!(paths(&each), vec![vec!["feature.txt"], vec!["main.txt"]]);
+
+        // Neither file differs from both parents, so the combined diff is empty.
+        let combined = get_edge_diffs(&repo, &edge, MergePolicy::Combined, &options).unwrap();
+        assert_eq!(paths(&combined), vec![Vec::<String>::new()]);
+
+        // A file written in the merge itself differs from both parents and is all that is combined.
+        test_repo.git(&["checkout", "-q", "feature"]);
+        let f2 = test_repo.commit("feature.txt", "feature\nmore\n", "f2");
+        test_repo.git(&["checkout", "-q", "main"]);
+        test_repo.git(&["merge", "-q", "--no-ff", "--no-commit", "feature"]);
+        test_repo.write("resolved.txt", "fixed up in the merge\n");
+        test_repo.git(&["add", "resolved.txt"]);
+        test_repo.git(&["commit", "-q", "-m", "m2"]);
+        let m2 = test_repo.git(&["rev-parse", "HEAD"]);
+        let [f2, m2] = [f2, m2].map(|id| ObjectId::from_hex(id.as_bytes()).unwrap());
+        let edge = CommitEdge { child: m2, parents: vec![m, f2] };
+
+        let combined = get_edge_diffs(&repo, &edge, MergePolicy::Combined, &options).unwrap();
+        assert_eq!(paths(&combined), vec![vec!["resolved.txt"]]);
+        assert_eq!(combined[0].changes[0].lines_added, 1);
+    }
+
+    #[test]
+    fn test_root_commit_diffs_against_empty_tree() {
+        let test_repo = TestRepo::new();
+        let root = test_repo.commit("a.txt", "1\n2\n", "root");
+        let repo = test_repo.open();
+        let root = ObjectId::from_hex(root.as_bytes()).unwrap();
+
+        let diff = get_parent_diff(&repo, None, &root, &DiffOptions::default()).unwrap();
+        assert_eq!(diff.changes.len(), 1);
+        assert_eq!(diff.changes[0].lines_added, 2);
+    }
+
+    #[test]
+    fn test_parallel_edge_diffs_keep_walk_order() {
+        let test_repo = TestRepo::new();
+        for i in 0..6 {
+            test_repo.commit(&format!("file{i}.txt"), "x\n", &format!("commit {i}"));
+        }
+        let repo = test_repo.open();
+        let mut edges = Vec::new();
+        CommitTreeIterator::new(&repo).unwrap().edges(|edge| {
+            edges.push(edge.clone());
+            Ok(())
+        }).unwrap();
+
+        let shared = repo.clone().into_sync();
+        let diffs = get_edge_diffs_parallel(&shared, &edges, MergePolicy::FirstParent, &DiffOptions::default(), 3).unwrap();
+        let commits: Vec<String> = diffs.iter().map(|diff| diff.new_commit.clone()).collect();
+        let expected: Vec<String> = edges.iter().map(|edge| edge.child.to_string()).collect();
+        assert_eq!(commits, expected);
+    }
+
+    fn walk(repo: &gix::Repository, options: WalkOptions) -> Vec<String> {
+        CommitTreeIterator::with_options(repo, options)
+            .unwrap()
+            .map(|id| id.unwrap().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_walk_revspec_ranges() {
+        let test_repo = TestRepo::new();
+        let a = test_repo.commit_at("f.txt", "a", "a", "2024-01-01T09:00:00+00:00");
+        let b = test_repo.commit_at("f.txt", "b", "b", "2024-01-02T09:00:00+00:00");
+        test_repo.git(&["checkout", "-q", "-b", "side", &a]);
+        let s = test_repo.commit_at("g.txt", "s", "s", "2024-01-03T09:00:00+00:00");
+        let repo = test_repo.open();
+
+        assert_eq!(walk(&repo, WalkOptions::default().with_revspec(format!("{a}..main"))), vec![b.clone()]);
+        assert_eq!(walk(&repo, WalkOptions::default().with_revspec("main...side")), vec![s, b]);
+    }
+
+    #[test]
+    fn test_walk_time_window() {
+        let test_repo = TestRepo::new();
+        test_repo.commit_at("f.txt", "a", "a", "2024-01-01T09:00:00+00:00");
+        let b = test_repo.commit_at("f.txt", "b", "b", "2024-01-02T09:00:00+00:00");
+        test_repo.commit_at("f.txt", "c", "c", "2024-01-03T09:00:00+00:00");
+        let repo = test_repo.open();
+
+        let day = |d: u32| Utc.with_ymd_and_hms(2024, 1, d, 0, 0, 0).unwrap();
+        let options = WalkOptions::default().with_since(day(2)).with_until(day(3));
+        assert_eq!(walk(&repo, options), vec![b]);
+    }
+
+    #[test]
+    fn test_walk_window_uses_author_time() {
+        let test_repo = TestRepo::new();
+        test_repo.commit_at("f.txt", "a", "a", "2024-01-01T09:00:00+00:00");
+        let rebased = test_repo.commit_with_env(
+            "f.txt",
+            "b",
+            "b",
+            &[("GIT_AUTHOR_DATE", "2024-01-01T10:00:00+00:00"), ("GIT_COMMITTER_DATE", "2024-01-03T09:00:00+00:00")],
+        );
+        let repo = test_repo.open();
+
+        let day = |d: u32| Utc.with_ymd_and_hms(2024, 1, d, 0, 0, 0).unwrap();
+        assert!(walk(&repo, WalkOptions::default().with_since(day(2))).is_empty());
+        assert_eq!(walk(&repo, WalkOptions::default().with_since(day(1)).with_until(day(2))).len(), 2);
+
+        let commit = GitCommit::try_from(&repo.find_commit(ObjectId::from_hex(rebased.as_bytes()).unwrap()).unwrap())
+            .unwrap();
+        assert!(!WalkOptions::default().with_since(day(2)).includes(&commit));
+        assert!(WalkOptions::default().with_until(day(2)).includes(&commit));
+    }
+
+    #[test]
+    fn test_walk_identity_roles() {
+        let test_repo = TestRepo::new();
+        let commit_by = |hour: u32, author: &str, committer: &str| {
+            let date = format!("2024-01-01T{hour:02}:00:00+00:00");
+            test_repo.commit_with_env(
+                "f.txt",
+                &hour.to_string(),
+                &hour.to_string(),
+                &[("GIT_AUTHOR_NAME", author), ("GIT_AUTHOR_EMAIL", &format!("{author}@example.com")),
+                  ("GIT_COMMITTER_NAME", committer), ("GIT_COMMITTER_EMAIL", &format!("{committer}@example.com")),
+                  ("GIT_AUTHOR_DATE", &date), ("GIT_COMMITTER_DATE", &date)],
+            )
+        };
+        let authored = commit_by(9, "jane", "bob");
+        commit_by(10, "bob", "bob");
+        let both = commit_by(11, "jane", "jane");
+        let committed = commit_by(12, "bob", "jane");
+        let repo = test_repo.open();
+
+        let jane = Identity { names: vec!["jane".into()], emails: Vec::new() };
+        let by = |role| {
+            let filter = IdentityFilter::new(jane.clone()).with_role(role);
+            walk(&repo, WalkOptions::default().with_identity(filter))
+        };
+        assert_eq!(by(SignatureRole::Author), [both.clone(), authored.clone()]);
+        assert_eq!(by(SignatureRole::Committer), [committed.clone(), both.clone()]);
+        assert_eq!(by(SignatureRole::Either), [committed, both, authored]);
+    }
+
+    #[test]
+    fn test_walk_reflog_revspec() {
+        let test_repo = TestRepo::new();
+        let earlier = (Utc::now() - chrono::Duration::days(3)).to_rfc3339();
+        let old = test_repo.commit_at("f.txt", "a", "a", &earlier);
+        test_repo.commit("f.txt", "b", "b");
+        let repo = test_repo.open();
+
+        assert_eq!(walk(&repo, WalkOptions::default().with_revspec("main@{yesterday}")), vec![old.clone()]);
+        assert_eq!(walk(&repo, WalkOptions::default().with_revspec("main@{2 days ago}")), vec![old]);
+    }
+
+    #[test]
+    fn test_rename_with_edit_keeps_its_hunks() {
+        let test_repo = TestRepo::new();
+        let original: String = (1..=10).map(|i| format!("line {i}\n")).collect();
+        test_repo.commit("old.txt", &original, "add");
+        test_repo.git(&["mv", "old.txt", "new.txt"]);
+        test_repo.commit("new.txt", &original.replace("line 5\n", "line five\n"), "rename and edit");
+        let repo = test_repo.open();
+
+        let diff = get_commit_diff(&repo, "HEAD~1", "HEAD", &DiffOptions::default()).unwrap();
+
+        assert_eq!(diff.changes.len(), 1);
+        let change = &diff.changes[0];
+        assert_eq!(change.change_type, ChangeType::Renamed);
+        assert_eq!((change.path.as_str(), change.source_path.as_deref()), ("new.txt", Some("old.txt")));
+        assert!(change.similarity.is_some_and(|similarity| similarity < 1.0), "{:?}", change.similarity);
+        assert_eq!((change.lines_added, change.lines_removed), (1, 1));
+        assert_eq!(change.hunks.len(), 1);
+        let edited: Vec<(DiffLineType, &str)> = change.hunks[0]
+            .lines
+            .iter()
+            .filter(|line| line.line_type != DiffLineType::Context)
+            .map(|line| (line.line_type, line.content.trim_end()))
+            .collect();
+        assert_eq!(edited, [(DiffLineType::Removed, "line 5"), (DiffLineType::Added, "line five")]);
+    }
+
+    #[test]
+    fn test_plain_text_is_unified_diff() {
+        let test_repo = TestRepo::new();
+        test_repo.commit("a.rs", "fn a() {}\nfn b() {}\n", "a");
+        test_repo.commit("a.rs", "fn a() {}\nfn c() {}\n", "b");
+        let repo = test_repo.open();
+
+        let diff = get_commit_diff(&repo, "HEAD~1", "HEAD", &DiffOptions::default()).unwrap();
+        let text = diff.changes[0].to_plain_text();
+
+        assert_eq!(text, "--- a/a.rs\n+++ b/a.rs\n@@ -1,2 +1,2 @@\n fn a() {}\n-fn b() {}\n+fn c() {}\n");
+    }
+
+    #[test]
+    fn test_excluded_paths_are_skipped() {
+        let test_repo = TestRepo::new();
+        test_repo.commit("src/a.rs", "fn a() {}\n", "a");
+        test_repo.write("Cargo.lock", "# generated\n");
+        test_repo.write("vendor/dep.c", "int x;\n");
+        test_repo.commit("src/a.rs", "fn a() {}\nfn b() {}\n", "b");
+        let repo = test_repo.open();
+
+        let options = DiffOptions {
+            paths: PathFilter::new(&[], &["Cargo.lock".into(), "vendor/".into()]),
+            ..DiffOptions::default()
+        };
+        let diff = get_commit_diff(&repo, "HEAD~1", "HEAD", &options).unwrap();
+
+        let paths: Vec<&str> = diff.changes.iter().map(|change| change.path.as_str()).collect();
+        assert_eq!(paths, ["src/a.rs"]);
+    }
+
+    #[test]
+    fn test_marked_files_are_listed_without_contents() {
+        let test_repo = TestRepo::new();
+        test_repo.write(".gitattributes", "*.lock linguist-generated\nvendor/** linguist-vendored\n");
+        test_repo.commit("src/a.rs", "fn a() {}\n", "a");
+        test_repo.write("Cargo.lock", "# generated\n");
+        test_repo.write("vendor/dep.c", "int x;\n");
+        test_repo.commit("src/a.rs", "fn a() {}\nfn b() {}\n", "b");
+        let repo = test_repo.open();
+
+        let diff = get_commit_diff(&repo, "HEAD~1", "HEAD", &DiffOptions::default()).unwrap();
+
+        let mut summary: Vec<_> = diff
+            .changes
+            .iter()
+            .map(|change| (change.path.as_str(), change.lines_added, change.attributes.labels()))
+            .collect();
+        summary.sort();
+        assert_eq!(
+            summary,
+            [("Cargo.lock", 0, vec!["generated"]), ("src/a.rs", 1, vec![]), ("vendor/dep.c", 0, vec!["vendored"])]
+        );
+        assert!(diff.changes.iter().all(|change| change.attributes.is_marked() == change.hunks.is_empty()));
+        assert!(diff.to_plain_text().contains("(generated, contents not diffed)"));
+    }
+
+    #[test]
+    fn test_deleted_files_keep_the_rules_they_were_committed_under() {
+        let test_repo = TestRepo::new();
+        test_repo.write(".gitattributes", "*.lock linguist-generated\n");
+        test_repo.commit("Cargo.lock", "# generated\n", "lock");
+        test_repo.git(&["rm", "-q", "Cargo.lock", ".gitattributes"]);
+        test_repo.git(&["commit", "-q", "-m", "drop lock"]);
+        let repo = test_repo.open();
+
+        let diff = get_commit_diff(&repo, "HEAD~1", "HEAD", &DiffOptions::default()).unwrap();
+
+        let lock = diff.changes.iter().find(|change| change.path == "Cargo.lock").unwrap();
+        assert_eq!((lock.change_type, lock.attributes.labels()), (ChangeType::Deleted, vec!["generated"]));
+        assert!(lock.hunks.is_empty());
+    }
+
+    #[test]
+    fn test_binary_files_have_sizes_instead_of_lines() {
+        let test_repo = TestRepo::new();
+        test_repo.commit("a.txt", "a\n", "a");
+        test_repo.write("logo.png", "\u{89}PNG\0\0\0");
+        test_repo.commit("data.bin", "\0\x01\x02\n", "add binaries");
+        test_repo.commit("data.bin", "\0\x01\x02\x03\x04\n", "grow binary");
+        let repo = test_repo.open();
+
+        let added = get_commit_diff(&repo, "HEAD~2", "HEAD~1", &DiffOptions::default()).unwrap();
+        let mut contents: Vec<_> = added.changes.iter().map(|c| (c.path.as_str(), c.content.clone(), c.hunks.len())).collect();
+        contents.sort_by_key(|(path, ..)| *path);
+        assert_eq!(
+            contents,
+            [
+                ("data.bin", FileContent::Binary { old_size: 0, new_size: 4 }, 0),
+                ("logo.png", FileContent::Binary { old_size: 0, new_size: 8 }, 0),
+            ]
+        );
+
+        let grown = get_commit_diff(&repo, "HEAD~1", "HEAD", &DiffOptions::default()).unwrap();
+        assert_eq!(grown.changes[0].content, FileContent::Binary { old_size: 4, new_size: 6 });
+        assert_eq!(grown.changes[0].lines_added, 0);
+    }
+
+    #[test]
+    fn test_lfs_pointers_are_parsed() {
+        let pointer = |size: u32| {
+            format!(
+                "version https://git-lfs.github.com/spec/v1\noid sha256:{}\nsize {size}\n",
+                "4d7a".repeat(16)
+            )
+        };
+        let test_repo = TestRepo::new();
+        test_repo.commit("model.bin", &pointer(100), "add model");
+        test_repo.commit("model.bin", &pointer(250), "retrain model");
+        let repo = test_repo.open();
+
+        let diff = get_commit_diff(&repo, "HEAD~1", "HEAD", &DiffOptions::default()).unwrap();
+        let change = &diff.changes[0];
+        assert_eq!(
+            change.content,
+            FileContent::Lfs {
+                oid: format!("sha256:{}", "4d7a".repeat(16)),
+                size: 250
+            }
+        );
+        assert_eq!((change.lines_added, change.lines_removed), (0, 0));
+        assert!(!change.is_diffed());
+        assert!(lfs_pointer(b"version 1\nsize 3\n").is_none());
     }
-    
-    let file_path = file_path.to_str()?;
-    let extension = std::path::Path::new(file_path)
-        .extension()
-        .and_then(|ext| ext.to_str())
-        .unwrap_or("");
-        
-    let syntax = PS.find_syntax_by_extension(extension)
-        .unwrap_or_else(|| PS.find_syntax_plain_text());
-    
-    let mut h = HighlightLines::new(syntax, &TS.themes["base16-ocean.dark"]);
-    
-    let ranges = h.highlight_line(content, &PS)
-        .map_err(|_| GitError::StdError("Failed to highlight line".into()))?;
-    let escaped = as_24_bit_terminal_escaped(&ranges[..], false);
-    
-    Ok(escaped)
 }
@@ -1,7 +1,4 @@
-use serde::{Deserialize, Serialize};
-use chrono::{DateTime, Utc};
 use gix::{self};
-use colored::*;
 
 // Each git commit has a unique hash
 
@@ -29,6 +26,8 @@ pub enum GitError {
     #[error(transparent)]
     RevParse(#[from] gix::revision::spec::parse::single::Error),
     #[error(transparent)]
+    RevSpec(#[from] gix::revision::spec::parse::Error),
+    #[error(transparent)]
     PeelToKind(#[from] gix::object::peel::to_kind::Error),
     #[error(transparent)]
     DiffCache(#[from] gix::repository::diff_resource_cache::Error),
@@ -49,6 +48,10 @@ pub enum GitError {
     #[error(transparent)]
     ReferenceIterInit(#[from] gix::reference::iter::init::Error),
     #[error(transparent)]
+    MergeBase(#[from] gix::repository::merge_base::Error),
+    #[error(transparent)]
+    AttributeStack(#[from] gix::config::attribute_stack::Error),
+    #[error(transparent)]
     StdError(#[from] Box<dyn std::error::Error + Send + Sync>),
     #[error(transparent)]
     Utf8Error(#[from] gix::bstr::Utf8Error),
@@ -0,0 +1,187 @@
+use super::diff::{DiffLine, DiffLineType};
+use colored::*;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::ops::Range;
+
+/// Number of unchanged lines shown around each change, matching `git diff`'s default.
+pub const DEFAULT_CONTEXT_LINES: u32 = 3;
+
+/// A span of lines in one side of a diff, using the 1-based `start,len` notation of unified diffs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LineRange {
+    pub start: u32,
+    pub len: u32,
+}
+
+/// A contiguous block of changes together with its surrounding context lines.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Hunk {
+    pub old_range: LineRange,
+    pub new_range: LineRange,
+    pub lines: Vec<DiffLine>,
+}
+
+impl LineRange {
+    /// Converts a 0-based half-open range of line indices into unified diff notation.
+    ///
+    /// As in `git diff`, an empty range points at the line *before* the insertion point.
+    fn from_indices(range: Range<u32>) -> Self {
+        let len = range.end - range.start;
+        let start = if len == 0 { range.start } else { range.start + 1 };
+        LineRange { start, len }
+    }
+}
+
+/// Groups the raw change ranges produced by a line diff into hunks with `context` lines of
+/// unchanged content around every change.
+///
+/// `changes` must be sorted and non-overlapping, as emitted by `imara-diff` sinks.
+pub fn build_hunks(
+    old_lines: &[&str],
+    new_lines: &[&str],
+    changes: &[(Range<u32>, Range<u32>)],
+    context: u32,
+) -> Vec<Hunk> {
+    let mut hunks = Vec::new();
+    let mut i = 0;
+
+    while i < changes.len() {
+        // Extend the group while the gap between consecutive changes is covered by context.
+        let mut j = i;
+        while j + 1 < changes.len() && changes[j + 1].0.start - changes[j].0.end <= 2 * context {
+            j += 1;
+        }
+
+        let (first_old, first_new) = &changes[i];
+        let (last_old, last_new) = &changes[j];
+        let lead = context.min(first_old.start).min(first_new.start);
+        let trail = context
+            .min(old_lines.len() as u32 - last_old.end)
+            .min(new_lines.len() as u32 - last_new.end);
+
+        let old_span = (first_old.start - lead)..(last_old.end + trail);
+        let new_span = (first_new.start - lead)..(last_new.end + trail);
+
+        let mut lines = Vec::new();
+        let mut old_pos = old_span.start;
+        for (before, after) in &changes[i..=j] {
+            push_lines(&mut lines, DiffLineType::Context, &old_lines[old_pos as usize..before.start as usize]);
+            push_lines(&mut lines, DiffLineType::Removed, &old_lines[before.start as usize..before.end as usize]);
+            push_lines(&mut lines, DiffLineType::Added, &new_lines[after.start as usize..after.end as usize]);
+            old_pos = before.end;
+        }
+        push_lines(&mut lines, DiffLineType::Context, &old_lines[old_pos as usize..old_span.end as usize]);
+
+        hunks.push(Hunk {
+            old_range: LineRange::from_indices(old_span),
+            new_range: LineRange::from_indices(new_span),
+            lines,
+        });
+        i = j + 1;
+    }
+
+    hunks
+}
+
+fn push_lines(out: &mut Vec<DiffLine>, line_type: DiffLineType, lines: &[&str]) {
+    out.extend(lines.iter().map(|line| DiffLine {
+        line_type,
+        content: line.to_string(),
+    }));
+}
+
+impl Hunk {
+    /// The `@@ -old +new @@` line that introduces the hunk.
+    pub fn header(&self) -> String {
+        format!("@@ -{} +{} @@", self.old_range, self.new_range)
+    }
+
+    /// Renders the hunk in unified diff format, without colours or syntax highlighting.
+    pub fn to_plain_text(&self) -> String {
+        let mut text = self.header() + "\n";
+        for line in &self.lines {
+            let prefix = match line.line_type {
+                DiffLineType::Added => '+',
+                DiffLineType::Removed => '-',
+                DiffLineType::Context => ' ',
+            };
+            text.push(prefix);
+            text.push_str(&line.content);
+            text.push('\n');
+        }
+        text
+    }
+}
+
+impl fmt::Display for LineRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.len == 1 {
+            write!(f, "{}", self.start)
+        } else {
+            write!(f, "{},{}", self.start, self.len)
+        }
+    }
+}
+
+impl fmt::Display for Hunk {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{}", self.header().cyan())?;
+        for line in &self.lines {
+            writeln!(f, "{}", line)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn types(hunk: &Hunk) -> String {
+        hunk.lines
+            .iter()
+            .map(|l| match l.line_type {
+                DiffLineType::Added => '+',
+                DiffLineType::Removed => '-',
+                DiffLineType::Context => ' ',
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_single_change_gets_context() {
+        let old = ["a", "b", "c", "d", "e", "f", "g"];
+        let new = ["a", "b", "c", "X", "e", "f", "g"];
+        let hunks = build_hunks(&old, &new, &[(3..4, 3..4)], 2);
+
+        assert_eq!(hunks.len(), 1);
+        assert_eq!(hunks[0].old_range, LineRange { start: 2, len: 5 });
+        assert_eq!(hunks[0].new_range, LineRange { start: 2, len: 5 });
+        assert_eq!(types(&hunks[0]), "  -+  ");
+    }
+
+    #[test]
+    fn test_distant_changes_split_into_hunks() {
+        let old: Vec<&str> = (0..20).map(|_| "x").collect();
+        let mut new = old.clone();
+        new[1] = "y";
+        new[18] = "y";
+        let hunks = build_hunks(&old, &new, &[(1..2, 1..2), (18..19, 18..19)], 3);
+
+        assert_eq!(hunks.len(), 2);
+        assert_eq!(hunks[0].old_range, LineRange { start: 1, len: 5 });
+        assert_eq!(hunks[1].old_range, LineRange { start: 16, len: 5 });
+    }
+
+    #[test]
+    fn test_pure_insertion_range() {
+        let old = ["a", "b"];
+        let new = ["a", "b", "c"];
+        let hunks = build_hunks(&old, &new, &[(2..2, 2..3)], 0);
+
+        assert_eq!(hunks[0].old_range, LineRange { start: 2, len: 0 });
+        assert_eq!(hunks[0].new_range, LineRange { start: 3, len: 1 });
+        assert_eq!(types(&hunks[0]), "+");
+    }
+}
@@ -0,0 +1,140 @@
+use super::error::GitError;
+use super::message::Trailers;
+use gix::bstr::BStr;
+use serde::{Deserialize, Serialize};
+
+/// The names and email addresses a single person commits under.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Identity {
+    pub names: Vec<String>,
+    pub emails: Vec<String>,
+}
+
+/// Which signature of a commit an [`IdentityFilter`] is checked against.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SignatureRole {
+    Author,
+    Committer,
+    /// Match if either the author or the committer belongs to the identity.
+    #[default]
+    Either,
+}
+
+/// Keeps only commits whose author and/or committer belongs to an [`Identity`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IdentityFilter {
+    pub identity: Identity,
+    pub role: SignatureRole,
+    /// Also keep commits that list the identity in a `Co-authored-by` trailer, so pair-programmed
+    /// work counts for everyone involved. Off unless enabled; ignored when matching committers only.
+    pub co_authors: bool,
+}
+
+impl Default for IdentityFilter {
+    fn default() -> Self {
+        IdentityFilter::new(Identity::default())
+    }
+}
+
+impl Identity {
+    /// Emails are compared case-insensitively, names case-insensitively after trimming whitespace.
+    pub fn matches(&self, name: &BStr, email: &BStr) -> bool {
+        let name = name.to_string();
+        let email = email.to_string();
+        self.emails
+            .iter()
+            .any(|candidate| candidate.trim().eq_ignore_ascii_case(email.trim()))
+            || self
+                .names
+                .iter()
+                .any(|candidate| candidate.trim().to_lowercase() == name.trim().to_lowercase())
+    }
+}
+
+impl IdentityFilter {
+    pub fn new(identity: Identity) -> Self {
+        IdentityFilter {
+            identity,
+            role: SignatureRole::default(),
+            co_authors: false,
+        }
+    }
+
+    pub fn with_role(mut self, role: SignatureRole) -> Self {
+        self.role = role;
+        self
+    }
+
+    pub fn with_co_authors(mut self, co_authors: bool) -> Self {
+        self.co_authors = co_authors;
+        self
+    }
+
+    pub fn matches(&self, commit: &gix::Commit<'_>) -> Result<bool, GitError> {
+        let author = || -> Result<bool, GitError> {
+            let author = commit.author()?;
+            Ok(self.identity.matches(author.name, author.email))
+        };
+        let committer = || -> Result<bool, GitError> {
+            let committer = commit.committer()?;
+            Ok(self.identity.matches(committer.name, committer.email))
+        };
+
+        let co_author = || -> Result<bool, GitError> {
+            if !self.co_authors {
+                return Ok(false);
+            }
+            let message = commit.message()?;
+            let body = message.body().map(|body| body.to_string());
+            Ok(Trailers::parse(body.as_deref())
+                .co_authors
+                .iter()
+                .any(|person| self.identity.matches(person.name.as_str().into(), person.email.as_str().into())))
+        };
+
+        match self.role {
+            SignatureRole::Author => Ok(author()? || co_author()?),
+            SignatureRole::Committer => committer(),
+            SignatureRole::Either => Ok(author()? || committer()? || co_author()?),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_identity_matches_any_alias_or_email() {
+        let identity = Identity {
+            names: vec!["Jane Doe".into(), "jdoe".into()],
+            emails: vec!["jane@example.com".into(), "jane@work.example".into()],
+        };
+
+        assert!(identity.matches("someone".into(), "Jane@Example.com".into()));
+        assert!(identity.matches("JDoe ".into(), "other@example.com".into()));
+        assert!(!identity.matches("John Doe".into(), "john@example.com".into()));
+    }
+
+    #[test]
+    fn test_co_authors_are_matched() {
+        let repo = crate::git::test_repo::TestRepo::new();
+        let hash = repo.commit(
+            "a.txt",
+            "a",
+            "Pair on the parser\n\nCo-authored-by: Jane Doe <jane@example.com>",
+        );
+        let git = repo.open();
+        let commit = git.find_commit(gix::ObjectId::from_hex(hash.as_bytes()).unwrap()).unwrap();
+        let jane = Identity {
+            names: vec!["Jane Doe".into()],
+            emails: vec!["jane@example.com".into()],
+        };
+
+        assert!(!IdentityFilter::new(jane.clone()).matches(&commit).unwrap());
+        assert!(IdentityFilter::new(jane.clone()).with_co_authors(true).matches(&commit).unwrap());
+        let committer = IdentityFilter::new(jane).with_co_authors(true).with_role(SignatureRole::Committer);
+        assert!(!committer.matches(&commit).unwrap());
+    }
+}
@@ -0,0 +1,279 @@
+use chrono::Duration;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// A name and email address as written in a trailer, e.g. `Jane Doe <jane@example.com>`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Person {
+    pub name: String,
+    pub email: String,
+}
+
+impl Person {
+    /// Parses `Name <email>`; a value without angle brackets is taken as a name alone.
+    pub fn parse(value: &str) -> Self {
+        match value.split_once('<') {
+            Some((name, rest)) => Person {
+                name: name.trim().to_string(),
+                email: rest.trim_end().trim_end_matches('>').trim().to_string(),
+            },
+            None => Person {
+                name: value.trim().to_string(),
+                email: String::new(),
+            },
+        }
+    }
+}
+
+/// A Conventional Commits header, `type(scope)!: description`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ConventionalCommit {
+    /// The type, lowercased, e.g. `feat` or `fix`.
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub scope: Option<String>,
+    /// Marked with `!` after the type or scope, or with a `BREAKING CHANGE` footer.
+    pub breaking: bool,
+    pub description: String,
+}
+
+/// The kind of work a commit represents, from its Conventional Commit type.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CommitCategory {
+    /// `feat`
+    Feature,
+    /// `fix`
+    Fix,
+    /// `refactor`
+    Refactor,
+    /// `perf`
+    Performance,
+    /// `docs`
+    Docs,
+    /// `test`
+    Test,
+    /// `build`, `ci`, `chore` and `style`
+    Chore,
+    /// Any other type, or a title that does not follow Conventional Commits.
+    Other,
+}
+
+impl fmt::Display for CommitCategory {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            CommitCategory::Feature => "feature",
+            CommitCategory::Fix => "fix",
+            CommitCategory::Refactor => "refactor",
+            CommitCategory::Performance => "performance",
+            CommitCategory::Docs => "docs",
+            CommitCategory::Test => "test",
+            CommitCategory::Chore => "chore",
+            CommitCategory::Other => "other",
+        };
+        f.write_str(name)
+    }
+}
+
+impl ConventionalCommit {
+    /// Parses `title` as a Conventional Commits header; `trailers` supply a `BREAKING CHANGE` footer.
+    pub fn parse(title: &str, trailers: &Trailers) -> Option<Self> {
+        let (prefix, description) = title.split_once(':')?;
+        let description = description.strip_prefix(' ')?.trim();
+        let (prefix, bang) = match prefix.strip_suffix('!') {
+            Some(prefix) => (prefix, true),
+            None => (prefix, false),
+        };
+        let (kind, scope) = match prefix.split_once('(') {
+            Some((kind, scope)) => (kind, Some(scope.strip_suffix(')')?)),
+            None => (prefix, None),
+        };
+        if kind.is_empty() || !kind.chars().all(|c| c.is_ascii_alphanumeric()) || description.is_empty() {
+            return None;
+        }
+        if scope.is_some_and(|scope| scope.is_empty() || scope.contains(['(', ')'])) {
+            return None;
+        }
+
+        Some(ConventionalCommit {
+            kind: kind.to_ascii_lowercase(),
+            scope: scope.map(str::to_string),
+            breaking: bang || trailers.breaking_change.is_some(),
+            description: description.to_string(),
+        })
+    }
+
+    pub fn category(&self) -> CommitCategory {
+        match self.kind.as_str() {
+            "feat" | "feature" => CommitCategory::Feature,
+            "fix" => CommitCategory::Fix,
+            "refactor" => CommitCategory::Refactor,
+            "perf" => CommitCategory::Performance,
+            "docs" => CommitCategory::Docs,
+            "test" | "tests" => CommitCategory::Test,
+            "build" | "ci" | "chore" | "style" => CommitCategory::Chore,
+            _ => CommitCategory::Other,
+        }
+    }
+}
+
+/// The trailers at the end of a commit message, e.g. `Co-authored-by: Jane <jane@example.com>`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Trailers {
+    /// `Co-authored-by`: everyone who worked on the commit besides its author.
+    pub co_authors: Vec<Person>,
+    pub signed_off_by: Vec<Person>,
+    /// `Refs`: referenced issues or tickets, split at commas and whitespace.
+    pub refs: Vec<String>,
+    /// `Time-Spent`, e.g. `1h30m`, `90m` or `1.5h`.
+    pub time_spent_minutes: Option<u32>,
+    /// `BREAKING CHANGE` or `BREAKING-CHANGE`.
+    pub breaking_change: Option<String>,
+    /// Every other trailer, in order.
+    pub other: Vec<(String, String)>,
+}
+
+impl Trailers {
+    /// Parses the trailer block, which is the last paragraph of `body` if every line in it is a
+    /// `Key: value` trailer or the indented continuation of one.
+    pub fn parse(body: Option<&str>) -> Self {
+        let mut trailers = Trailers::default();
+        let Some(paragraph) = body.and_then(|body| body.trim_end().rsplit("\n\n").next()) else {
+            return trailers;
+        };
+
+        let mut entries: Vec<(String, String)> = Vec::new();
+        for line in paragraph.lines() {
+            if line.starts_with([' ', '\t']) {
+                match entries.last_mut() {
+                    Some((_, value)) => {
+                        value.push(' ');
+                        value.push_str(line.trim());
+                    }
+                    None => return trailers,
+                }
+                continue;
+            }
+            match trailer(line) {
+                Some((key, value)) => entries.push((key.to_string(), value.to_string())),
+                None => return trailers,
+            }
+        }
+
+        for (key, value) in entries {
+            match key.to_ascii_lowercase().as_str() {
+                "co-authored-by" => trailers.co_authors.push(Person::parse(&value)),
+                "signed-off-by" => trailers.signed_off_by.push(Person::parse(&value)),
+                "refs" => trailers.refs.extend(
+                    value
+                        .split(|c: char| c == ',' || c.is_whitespace())
+                        .filter(|reference| !reference.is_empty())
+                        .map(str::to_string),
+                ),
+                "time-spent" => trailers.time_spent_minutes = parse_minutes(&value),
+                "breaking change" | "breaking-change" => trailers.breaking_change = Some(value),
+                _ => trailers.other.push((key, value)),
+            }
+        }
+        trailers
+    }
+
+    pub fn time_spent(&self) -> Option<Duration> {
+        self.time_spent_minutes.map(|minutes| Duration::minutes(minutes.into()))
+    }
+}
+
+/// Splits `Key: value`, where the key is a single token or `BREAKING CHANGE`.
+fn trailer(line: &str) -> Option<(&str, &str)> {
+    let (key, value) = line.split_once(':')?;
+    let is_token = !key.is_empty() && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '-');
+    (is_token || key == "BREAKING CHANGE").then(|| (key, value.trim()))
+}
+
+/// Parses durations like `2h`, `1h30m`, `1h 30min`, `1.5h`, `90m` or a bare number of minutes.
+fn parse_minutes(value: &str) -> Option<u32> {
+    let value = value.trim().to_ascii_lowercase();
+    if let Ok(minutes) = value.parse::<u32>() {
+        return Some(minutes);
+    }
+
+    let mut total = 0.0;
+    let mut rest = value.as_str();
+    while !rest.is_empty() {
+        let number_end = rest.find(|c: char| !(c.is_ascii_digit() || c == '.'))?;
+        let number: f64 = rest[..number_end].parse().ok()?;
+        rest = rest[number_end..].trim_start();
+        let unit_end = rest.find(|c: char| !c.is_ascii_alphabetic()).unwrap_or(rest.len());
+        total += number
+            * match &rest[..unit_end] {
+                "h" | "hr" | "hrs" | "hour" | "hours" => 60.0,
+                "m" | "min" | "mins" | "minute" | "minutes" => 1.0,
+                _ => return None,
+            };
+        rest = rest[unit_end..].trim_start();
+    }
+    Some(total.round() as u32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_conventional_headers() {
+        let none = Trailers::default();
+        let parse = |title| ConventionalCommit::parse(title, &none);
+
+        let header = parse("feat(parser)!: accept trailing commas").unwrap();
+        assert_eq!(header.kind, "feat");
+        assert_eq!(header.scope.as_deref(), Some("parser"));
+        assert!(header.breaking);
+        assert_eq!(header.description, "accept trailing commas");
+        assert_eq!(header.category(), CommitCategory::Feature);
+
+        assert_eq!(parse("Fix: typo").unwrap().category(), CommitCategory::Fix);
+        assert_eq!(parse("chore: bump deps").unwrap().scope, None);
+        assert!(parse("Merge branch 'main'").is_none());
+        assert!(parse("fix(): empty scope").is_none());
+        assert!(parse("see http://example.com").is_none());
+        assert!(parse("fix:no space").is_none());
+    }
+
+    #[test]
+    fn test_trailers_in_last_paragraph() {
+        let body = "Reworked the cache.\n\nSome: thing in the middle.\n\n\
+                    Co-authored-by: Jane Doe <jane@example.com>\n\
+                    Co-authored-by: Bob <bob@example.com>\n\
+                    Refs: #12, ABC-3\n\
+                    Time-Spent: 1h 30m\n\
+                    BREAKING CHANGE: the cache format\n  changed\n\
+                    Signed-off-by: Sam <sam@example.com>\n";
+        let trailers = Trailers::parse(Some(body));
+
+        assert_eq!(
+            trailers.co_authors,
+            [Person::parse("Jane Doe <jane@example.com>"), Person::parse("Bob <bob@example.com>")]
+        );
+        assert_eq!(trailers.co_authors[0].email, "jane@example.com");
+        assert_eq!(trailers.refs, ["#12", "ABC-3"]);
+        assert_eq!(trailers.time_spent_minutes, Some(90));
+        assert_eq!(trailers.breaking_change.as_deref(), Some("the cache format changed"));
+        assert_eq!(trailers.signed_off_by[0].name, "Sam");
+        assert!(trailers.other.is_empty());
+
+        let prose = Trailers::parse(Some("Note: this is prose\nthat is not a trailer block.\n"));
+        assert_eq!(prose, Trailers::default());
+        assert!(ConventionalCommit::parse("fix: cache", &trailers).unwrap().breaking);
+    }
+
+    #[test]
+    fn test_time_spent_formats() {
+        assert_eq!(parse_minutes("45"), Some(45));
+        assert_eq!(parse_minutes("2h"), Some(120));
+        assert_eq!(parse_minutes("1.5h"), Some(90));
+        assert_eq!(parse_minutes("1h30min"), Some(90));
+        assert_eq!(parse_minutes("20 minutes"), Some(20));
+        assert_eq!(parse_minutes("soon"), None);
+    }
+}
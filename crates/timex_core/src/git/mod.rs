@@ -1,24 +1,45 @@
+pub mod attributes;
 pub mod diff;
 pub mod error;
+pub mod hunk;
+pub mod identity;
+pub mod message;
+pub mod paths;
+#[cfg(test)]
+pub(crate) mod test_repo;
+use diff::{CommitTreeIterator, WalkOptions};
 use error::GitError;
+use message::{CommitCategory, ConventionalCommit, Trailers};
 use serde::{Deserialize, Serialize};
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, FixedOffset, NaiveDate};
+use std::collections::BTreeMap;
 use gix::{self};
+use gix::bstr::ByteSlice;
 use colored::*;
 
 // Each git commit has a unique hash
 
 
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct GitCommit {
     pub hash: String,
     pub title: String,
     pub body: Option<String>,
     pub author: String,
-    pub date: DateTime<Utc>,
-    #[serde(skip)]
-    diff: Option<String>, // We'll skip serializing the diff as it can be large
+    pub author_email: String,
+    pub committer: String,
+    pub committer_email: String,
+    /// When the change was authored, in the author's timezone.
+    pub author_date: DateTime<FixedOffset>,
+    /// When the commit was created, in the committer's timezone; differs from the author date after rebases.
+    pub committer_date: DateTime<FixedOffset>,
+    /// The title parsed as a Conventional Commits header, if it is one.
+    #[serde(default)]
+    pub conventional: Option<ConventionalCommit>,
+    /// The trailers at the end of the body.
+    #[serde(default)]
+    pub trailers: Trailers,
 }
 
 
@@ -27,33 +48,87 @@ impl<'repo> TryFrom<&gix::Commit<'repo>> for GitCommit {
 
     fn try_from(commit: &gix::Commit) -> Result<Self, Self::Error> {
         let hash = commit.id.to_hex().to_string();
-        let author = commit.author()?.name.to_string();
-        let time = commit.time()?;
+        let author_signature = commit.author()?;
+        let author = author_signature.name.to_string();
+        let author_email = author_signature.email.to_string();
+        let author_date = to_datetime(author_signature.time);
+        let committer_signature = commit.committer()?;
+        let committer = committer_signature.name.to_string();
+        let committer_email = committer_signature.email.to_string();
+        let committer_date = to_datetime(committer_signature.time);
         let msg = commit.message()?;
-        let title = msg.title.to_string();
+        let title = msg.title.trim_end().to_str_lossy().into_owned();
         let body = msg.body().map(|b| b.to_string());
-        
-        
-        Ok(GitCommit {
+
+        let mut commit = GitCommit {
             hash,
             title,
             body,
             author,
-            date: DateTime::from_timestamp(time.seconds, 0).unwrap_or_default(),
-            diff: None,
-        })
+            author_email,
+            committer,
+            committer_email,
+            author_date,
+            committer_date,
+            conventional: None,
+            trailers: Trailers::default(),
+        };
+        commit.parse_message();
+        Ok(commit)
     }
 }
 
+/// Converts a git timestamp into a datetime that keeps the signature's UTC offset.
+fn to_datetime(time: gix::date::Time) -> DateTime<FixedOffset> {
+    let offset = FixedOffset::east_opt(time.offset).unwrap_or_else(|| FixedOffset::east_opt(0).unwrap());
+    DateTime::from_timestamp(time.seconds, 0)
+        .unwrap_or_default()
+        .with_timezone(&offset)
+}
+
 impl GitCommit {
+    /// Fills in [`GitCommit::conventional`] and [`GitCommit::trailers`] from the title and body.
+    pub fn parse_message(&mut self) {
+        self.trailers = Trailers::parse(self.body.as_deref());
+        self.conventional = ConventionalCommit::parse(&self.title, &self.trailers);
+    }
+
+    /// The kind of work the commit represents; [`CommitCategory::Other`] unless the title follows
+    /// Conventional Commits.
+    pub fn category(&self) -> CommitCategory {
+        self.conventional.as_ref().map_or(CommitCategory::Other, ConventionalCommit::category)
+    }
+
+    /// The calendar day the work was done on, in the author's local time.
+    pub fn work_day(&self) -> NaiveDate {
+        self.author_date.date_naive()
+    }
+
+    /// Groups commits by [`GitCommit::work_day`].
+    pub fn by_work_day(commits: &[GitCommit]) -> BTreeMap<NaiveDate, Vec<&GitCommit>> {
+        let mut days: BTreeMap<NaiveDate, Vec<&GitCommit>> = BTreeMap::new();
+        for commit in commits {
+            days.entry(commit.work_day()).or_default().push(commit);
+        }
+        days
+    }
+
     /// Returns all commits in the repository at the given path
     pub fn from_repo(repo_path:impl Into<std::path::PathBuf>) -> Result<Vec<Self>, GitError> {
+        Self::from_repo_with(repo_path, &WalkOptions::default())
+    }
+
+    /// Returns the commits that pass the filters in `options`, newest first.
+    ///
+    /// Without a revspec in `options`, the commits reachable from `HEAD` are returned.
+    pub fn from_repo_with(repo_path: impl Into<std::path::PathBuf>, options: &WalkOptions) -> Result<Vec<Self>, GitError> {
         let repo = gix::open(repo_path)?;
+        let mut options = options.clone();
+        options.revspec.get_or_insert_with(|| "HEAD".to_string());
+
         let mut commits = Vec::new();
-        
-        for info in repo.head_commit()?.ancestors().all()? {
-            let info = info?;
-            let commit = info.object()?;
+        for id in CommitTreeIterator::with_options(&repo, options)? {
+            let commit = repo.find_commit(id?)?;
             commits.push(GitCommit::try_from(&commit)?);
         }
         
@@ -79,10 +154,16 @@ impl std::fmt::Display for GitCommit {
         writeln!(f, "{} {}", "commit".bright_yellow(), self.hash.bright_white())?;
         
         if !self.author.is_empty() {
-            writeln!(f, "{}: {}", "Author".bright_blue(), self.author)?;
+            writeln!(f, "{}: {} <{}>", "Author".bright_blue(), self.author, self.author_email)?;
+        }
+        for person in &self.trailers.co_authors {
+            writeln!(f, "{}: {} <{}>", "Co-author".bright_blue(), person.name, person.email)?;
         }
         
-        writeln!(f, "{}: {}", "Date".bright_blue(), self.date)?;
+        writeln!(f, "{}: {}", "Date".bright_blue(), self.author_date)?;
+        if self.committer_date != self.author_date {
+            writeln!(f, "{}: {}", "CommitDate".bright_blue(), self.committer_date)?;
+        }
         
         if !self.title.is_empty() {
             writeln!(f, "{}: {}", "Title".bright_blue(), self.title.bright_green())?;
@@ -102,6 +183,29 @@ impl std::fmt::Display for GitCommit {
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_repo::TestRepo;
 
+    #[test]
+    fn test_commit_keeps_local_times() {
+        let repo = TestRepo::new();
+        repo.commit_with_env(
+            "a.txt",
+            "a",
+            "late night work",
+            &[
+                ("GIT_AUTHOR_DATE", "2024-01-01T23:30:00-08:00"),
+                ("GIT_COMMITTER_DATE", "2024-01-05T10:00:00+01:00"),
+            ],
+        );
 
+        let commits = GitCommit::from_repo(repo.path()).unwrap();
+        let commit = &commits[0];
 
+        assert_eq!(commit.author_date.to_rfc3339(), "2024-01-01T23:30:00-08:00");
+        assert_eq!(commit.committer_date.to_rfc3339(), "2024-01-05T10:00:00+01:00");
+        assert_eq!(commit.work_day(), NaiveDate::from_ymd_opt(2024, 1, 1).unwrap());
+    }
+}
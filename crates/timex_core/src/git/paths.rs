@@ -0,0 +1,91 @@
+use gix::bstr::{BStr, ByteSlice};
+use gix::glob::pattern::Case;
+use gix::glob::wildmatch::Mode;
+use gix::glob::Pattern;
+
+/// Selects the files of a diff by `.gitignore`-style globs on their repository-relative path.
+///
+/// A pattern without a slash matches at any depth (`Cargo.lock`), one with a slash is anchored at
+/// the repository root (`src/generated/*.rs`), `**` spans directories and a trailing slash matches
+/// a directory together with everything below it (`vendor/`).
+#[derive(Debug, Clone, Default)]
+pub struct PathFilter {
+    include: Vec<Pattern>,
+    exclude: Vec<Pattern>,
+}
+
+impl PathFilter {
+    /// Keeps the paths matching any of `include`, or every path if it is empty, unless they match
+    /// one of `exclude`. Empty patterns are ignored.
+    pub fn new(include: &[String], exclude: &[String]) -> Self {
+        let parse = |patterns: &[String]| {
+            patterns
+                .iter()
+                .filter_map(|pattern| Pattern::from_bytes_without_negation(pattern.as_bytes()))
+                .collect()
+        };
+        PathFilter {
+            include: parse(include),
+            exclude: parse(exclude),
+        }
+    }
+
+    /// Whether the file at `path` is part of the diff.
+    pub fn allows(&self, path: &BStr) -> bool {
+        (self.include.is_empty() || matches_any(&self.include, path)) && !matches_any(&self.exclude, path)
+    }
+}
+
+/// Whether `path` or one of the directories containing it matches any of `patterns`.
+fn matches_any(patterns: &[Pattern], path: &BStr) -> bool {
+    let directories = path
+        .find_iter("/")
+        .map(|end| (path[..end].as_bstr(), true));
+    std::iter::once((path, false)).chain(directories).any(|(candidate, is_dir)| {
+        let basename = candidate.rfind_byte(b'/').map(|slash| slash + 1);
+        patterns.iter().any(|pattern| {
+            pattern.matches_repo_relative_path(candidate, basename, Some(is_dir), Case::Sensitive, Mode::NO_MATCH_SLASH_LITERAL)
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn filter(include: &[&str], exclude: &[&str]) -> PathFilter {
+        let strings = |patterns: &[&str]| patterns.iter().map(|p| p.to_string()).collect::<Vec<_>>();
+        PathFilter::new(&strings(include), &strings(exclude))
+    }
+
+    fn allows(filter: &PathFilter, path: &str) -> bool {
+        filter.allows(path.into())
+    }
+
+    #[test]
+    fn test_exclude_follows_gitignore_rules() {
+        let filter = filter(&[], &["Cargo.lock", "vendor/", "*.min.js", "/docs/*.html"]);
+
+        assert!(!allows(&filter, "Cargo.lock"));
+        assert!(!allows(&filter, "crates/core/Cargo.lock"));
+        assert!(!allows(&filter, "vendor/lib/a.c"));
+        assert!(!allows(&filter, "web/vendor/b.c"));
+        assert!(!allows(&filter, "static/app.min.js"));
+        assert!(!allows(&filter, "docs/index.html"));
+        assert!(allows(&filter, "src/docs/index.html"));
+        assert!(allows(&filter, "src/vendor.rs"));
+        assert!(allows(&filter, "Cargo.toml"));
+    }
+
+    #[test]
+    fn test_include_narrows_and_exclude_wins() {
+        let filter = filter(&["src/**/*.rs", "README.md"], &["src/generated/"]);
+
+        assert!(allows(&filter, "src/main.rs"));
+        assert!(allows(&filter, "src/git/diff.rs"));
+        assert!(allows(&filter, "README.md"));
+        assert!(!allows(&filter, "src/generated/schema.rs"));
+        assert!(!allows(&filter, "tests/it.rs"));
+        assert!(allows(&PathFilter::default(), "anything/at/all"));
+    }
+}
@@ -0,0 +1,80 @@
+//! Throwaway git repositories for tests, built with the `git` command line.
+
+use std::path::Path;
+use std::process::Command;
+use tempfile::TempDir;
+
+pub struct TestRepo {
+    dir: TempDir,
+}
+
+impl TestRepo {
+    pub fn new() -> Self {
+        let repo = TestRepo {
+            dir: TempDir::new().unwrap(),
+        };
+        repo.git(&["init", "-q", "-b", "main"]);
+        repo.git(&["config", "user.name", "Test User"]);
+        repo.git(&["config", "user.email", "test@example.com"]);
+        repo
+    }
+
+    pub fn path(&self) -> &Path {
+        self.dir.path()
+    }
+
+    pub fn open(&self) -> gix::Repository {
+        gix::open(self.path()).unwrap()
+    }
+
+    /// Runs `git` in the repository and returns its trimmed stdout.
+    pub fn git(&self, args: &[&str]) -> String {
+        self.git_with_env(args, &[])
+    }
+
+    pub fn git_with_env(&self, args: &[&str], env: &[(&str, &str)]) -> String {
+        let output = Command::new("git")
+            .args(args)
+            .current_dir(self.path())
+            .envs(env.iter().copied())
+            .output()
+            .unwrap();
+        assert!(
+            output.status.success(),
+            "git {:?} failed: {}",
+            args,
+            String::from_utf8_lossy(&output.stderr)
+        );
+        String::from_utf8_lossy(&output.stdout).trim().to_string()
+    }
+
+    pub fn write(&self, file: &str, content: &str) {
+        let path = self.path().join(file);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).unwrap();
+        }
+        std::fs::write(path, content).unwrap();
+    }
+
+    /// Writes `file`, commits it with `message` and returns the new commit id.
+    pub fn commit(&self, file: &str, content: &str, message: &str) -> String {
+        self.commit_with_env(file, content, message, &[])
+    }
+
+    /// Like [`TestRepo::commit`], with author and committer dates set to `date`, e.g. `2024-01-01T09:00:00+00:00`.
+    pub fn commit_at(&self, file: &str, content: &str, message: &str, date: &str) -> String {
+        self.commit_with_env(
+            file,
+            content,
+            message,
+            &[("GIT_AUTHOR_DATE", date), ("GIT_COMMITTER_DATE", date)],
+        )
+    }
+
+    pub fn commit_with_env(&self, file: &str, content: &str, message: &str, env: &[(&str, &str)]) -> String {
+        self.write(file, content);
+        self.git(&["add", "-A"]);
+        self.git_with_env(&["commit", "-q", "-m", message], env);
+        self.git(&["rev-parse", "HEAD"])
+    }
+}
@@ -1,3 +1,10 @@
+// `GitError` wraps gix errors by value, which trips this lint on every fallible function.
+#![allow(clippy::result_large_err)]
+
 pub mod git;
+pub mod pool;
 pub mod project;
+pub mod render;
+pub mod store;
+pub mod summary;
 
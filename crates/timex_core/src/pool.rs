@@ -0,0 +1,133 @@
+use serde::{Deserialize, Serialize};
+use std::num::NonZeroUsize;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+/// Limits on how much work runs at the same time.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct ConcurrencyConfig {
+    /// How many repositories are walked at the same time.
+    pub max_repos: usize,
+    /// Worker threads computing diffs, shared by the repositories walked at the same time; `0` uses
+    /// one per CPU.
+    pub diff_threads: usize,
+}
+
+impl Default for ConcurrencyConfig {
+    fn default() -> Self {
+        ConcurrencyConfig {
+            max_repos: 4,
+            diff_threads: 0,
+        }
+    }
+}
+
+impl ConcurrencyConfig {
+    pub fn repo_limit(&self) -> usize {
+        self.max_repos.max(1)
+    }
+
+    /// Worker threads computing the diffs of one repository: an equal share of
+    /// [`ConcurrencyConfig::diff_threads`] for each of the repositories walked at once, and at least one.
+    pub fn diff_thread_count(&self) -> usize {
+        let total = match self.diff_threads {
+            0 => std::thread::available_parallelism().map_or(1, NonZeroUsize::get),
+            threads => threads,
+        };
+        (total / self.repo_limit()).max(1)
+    }
+}
+
+/// Applies `f` to every item on at most `workers` threads, returning the results in item order.
+///
+/// Every worker calls `init` once to set up its own state, e.g. a thread-local repository handle,
+/// and passes it to `f` for each item it picks up.
+pub fn map_bounded<T, S, R>(
+    items: &[T],
+    workers: usize,
+    init: impl Fn() -> S + Sync,
+    f: impl Fn(&mut S, &T) -> R + Sync,
+) -> Vec<R>
+where
+    T: Sync,
+    R: Send,
+{
+    let workers = workers.clamp(1, items.len().max(1));
+    if workers == 1 {
+        let mut state = init();
+        return items.iter().map(|item| f(&mut state, item)).collect();
+    }
+
+    let next = AtomicUsize::new(0);
+    let results: Mutex<Vec<Option<R>>> = Mutex::new(items.iter().map(|_| None).collect());
+    std::thread::scope(|scope| {
+        for _ in 0..workers {
+            scope.spawn(|| {
+                let mut state = init();
+                loop {
+                    let index = next.fetch_add(1, Ordering::Relaxed);
+                    let Some(item) = items.get(index) else { break };
+                    let result = f(&mut state, item);
+                    results.lock().unwrap()[index] = Some(result);
+                }
+            });
+        }
+    });
+
+    results
+        .into_inner()
+        .unwrap()
+        .into_iter()
+        .map(|result| result.expect("every item is processed by a worker"))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_results_keep_item_order() {
+        let items: Vec<u64> = (0..50).collect();
+        let results = map_bounded(&items, 4, || (), |_, item| {
+            std::thread::sleep(Duration::from_millis(50 - item));
+            item * 2
+        });
+
+        assert_eq!(results, items.iter().map(|item| item * 2).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_diff_threads_are_shared_by_repositories() {
+        let config = |max_repos, diff_threads| ConcurrencyConfig { max_repos, diff_threads };
+
+        assert_eq!(config(4, 8).diff_thread_count(), 2);
+        assert_eq!(config(4, 2).diff_thread_count(), 1);
+        assert_eq!(config(1, 8).diff_thread_count(), 8);
+        assert_eq!(config(0, 8).diff_thread_count(), 8);
+    }
+
+    #[test]
+    fn test_concurrency_is_bounded() {
+        let running = AtomicUsize::new(0);
+        let peak = AtomicUsize::new(0);
+        let workers = AtomicUsize::new(0);
+
+        map_bounded(
+            &[(); 20],
+            3,
+            || workers.fetch_add(1, Ordering::SeqCst),
+            |_, _| {
+                let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+                peak.fetch_max(now, Ordering::SeqCst);
+                std::thread::sleep(Duration::from_millis(5));
+                running.fetch_sub(1, Ordering::SeqCst);
+            },
+        );
+
+        assert_eq!(workers.load(Ordering::SeqCst), 3);
+        assert!(peak.load(Ordering::SeqCst) <= 3);
+    }
+}
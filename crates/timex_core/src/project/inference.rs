@@ -0,0 +1,199 @@
+use super::{EntrySource, Project, TimeEntry};
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Settings for turning commit timestamps into working sessions.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct InferenceConfig {
+    /// Commits further apart than this start a new session.
+    pub max_gap_minutes: u32,
+    /// Time credited before the first commit of a session, for work that led up to it.
+    pub lead_in_minutes: u32,
+}
+
+impl Default for InferenceConfig {
+    fn default() -> Self {
+        InferenceConfig {
+            max_gap_minutes: 120,
+            lead_in_minutes: 30,
+        }
+    }
+}
+
+impl InferenceConfig {
+    pub fn max_gap(&self) -> Duration {
+        Duration::minutes(self.max_gap_minutes.into())
+    }
+
+    pub fn lead_in(&self) -> Duration {
+        Duration::minutes(self.lead_in_minutes.into())
+    }
+}
+
+/// A moment work was recorded at, e.g. a commit, and the ticket it was for.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WorkItem {
+    pub time: DateTime<Utc>,
+    pub task: Option<String>,
+    /// The UTC offset, in seconds east, of the author, passed on to [`TimeEntry::utc_offset`].
+    pub utc_offset: Option<i32>,
+}
+
+/// Groups commit timestamps into sessions of continuous work on `project`.
+///
+/// A session runs from its first commit minus the lead-in up to its last commit. The lead-in
+/// never reaches back into the previous session, so sessions never overlap.
+pub fn infer_sessions(
+    project: &Project,
+    timestamps: &[DateTime<Utc>],
+    config: &InferenceConfig,
+) -> Vec<TimeEntry> {
+    let items: Vec<WorkItem> = timestamps.iter().map(|&time| WorkItem {
+            time,
+            task: None,
+            utc_offset: None,
+        }).collect();
+    infer_task_sessions(project, &items, config)
+}
+
+/// Like [`infer_sessions`], but splits each session between the tasks of its items.
+///
+/// The time leading up to an item, from the item before it or the start of the session, is
+/// credited to the item's task, and back-to-back stretches on the same task are joined.
+///
+/// An entry backed by `n` items has a confidence of `n / (n + 1)`: a lone commit says little
+/// about how long the work before it took, while a steady run of commits pins it down.
+pub fn infer_task_sessions(project: &Project, items: &[WorkItem], config: &InferenceConfig) -> Vec<TimeEntry> {
+    let mut items = items.to_vec();
+    // Of several items at the same time, keep one with a task.
+    items.sort_by_key(|item| (item.time, item.task.is_none()));
+    items.dedup_by_key(|item| item.time);
+
+    let mut entries: Vec<TimeEntry> = Vec::new();
+    let mut session: Vec<WorkItem> = Vec::new();
+    for item in items {
+        if session.last().is_some_and(|last| item.time - last.time > config.max_gap()) {
+            let segments = session_entries(project, &session, &entries, config);
+            entries.extend(segments);
+            session.clear();
+        }
+        session.push(item);
+    }
+    if !session.is_empty() {
+        let segments = session_entries(project, &session, &entries, config);
+        entries.extend(segments);
+    }
+
+    entries
+}
+
+fn session_entries(
+    project: &Project,
+    session: &[WorkItem],
+    previous: &[TimeEntry],
+    config: &InferenceConfig,
+) -> Vec<TimeEntry> {
+    let mut start_time = session[0].time - config.lead_in();
+    if let Some(previous) = previous.last() {
+        start_time = start_time.max(previous.end_time);
+    }
+
+    let mut entries: Vec<(TimeEntry, u32)> = Vec::new();
+    for item in session {
+        match entries.last_mut() {
+            Some((last, count)) if last.task == item.task => {
+                last.end_time = item.time;
+                *count += 1;
+            }
+            _ if item.time > start_time => {
+                let mut entry = project.time_entry(item.task.clone(), start_time, item.time, EntrySource::Inferred);
+                entry.utc_offset = item.utc_offset;
+                entries.push((entry, 1));
+            }
+            _ => {}
+        }
+        start_time = item.time;
+    }
+    if entries.is_empty() {
+        let mut entry = project.time_entry(session[0].task.clone(), start_time, start_time, EntrySource::Inferred);
+        entry.utc_offset = session[0].utc_offset;
+        entries.push((entry, 1));
+    }
+    entries
+        .into_iter()
+        .map(|(entry, count)| TimeEntry {
+            confidence: f64::from(count) / f64::from(count + 1),
+            ..entry
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn project() -> Project {
+        Project::for_test("P1", "/tmp/project1")
+    }
+
+    fn at(hour: u32, minute: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 1, 1, hour, minute, 0).unwrap()
+    }
+
+    #[test]
+    fn test_gap_splits_sessions() {
+        let commits = [at(9, 0), at(10, 0), at(11, 30), at(15, 0), at(15, 45)];
+        let entries = infer_sessions(&project(), &commits, &InferenceConfig::default());
+
+        assert_eq!(entries.len(), 2);
+        assert_eq!((entries[0].start_time, entries[0].end_time), (at(8, 30), at(11, 30)));
+        assert_eq!((entries[1].start_time, entries[1].end_time), (at(14, 30), at(15, 45)));
+    }
+
+    #[test]
+    fn test_lead_in_does_not_overlap_previous_session() {
+        let config = InferenceConfig {
+            max_gap_minutes: 30,
+            lead_in_minutes: 90,
+        };
+        let entries = infer_sessions(&project(), &[at(10, 0), at(9, 0)], &config);
+
+        assert_eq!((entries[0].start_time, entries[0].end_time), (at(7, 30), at(9, 0)));
+        assert_eq!((entries[1].start_time, entries[1].end_time), (at(9, 0), at(10, 0)));
+    }
+
+    #[test]
+    fn test_sessions_are_split_between_tasks() {
+        let item = |hour, minute, task: Option<&str>| WorkItem {
+            time: at(hour, minute),
+            task: task.map(str::to_string),
+            utc_offset: None,
+        };
+        let items = [
+            item(9, 0, Some("ABC-1")),
+            item(10, 0, Some("ABC-1")),
+            item(11, 0, Some("ABC-2")),
+            item(11, 30, None),
+            item(15, 0, Some("ABC-2")),
+        ];
+        let entries = infer_task_sessions(&project(), &items, &InferenceConfig::default());
+        let spans: Vec<_> = entries
+            .iter()
+            .map(|entry| (entry.start_time, entry.end_time, entry.task.as_deref()))
+            .collect();
+
+        assert_eq!(
+            spans,
+            [
+                (at(8, 30), at(10, 0), Some("ABC-1")),
+                (at(10, 0), at(11, 0), Some("ABC-2")),
+                (at(11, 0), at(11, 30), None),
+                (at(14, 30), at(15, 0), Some("ABC-2")),
+            ]
+        );
+        assert_eq!(entries[0].confidence, 2.0 / 3.0);
+        assert_eq!(entries[3].source, EntrySource::Inferred);
+    }
+}
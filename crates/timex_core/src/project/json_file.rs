@@ -0,0 +1,67 @@
+//! Small JSON state files kept between runs, such as the sync state and the punch clock.
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// Reads the value at `path`, starting from the default if the file does not exist yet.
+pub(crate) fn load<T, E>(path: &Path) -> Result<T, E>
+where
+    T: DeserializeOwned + Default,
+    E: From<std::io::Error> + From<serde_json::Error>,
+{
+    match std::fs::read_to_string(path) {
+        Ok(content) => Ok(serde_json::from_str(&content)?),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(T::default()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Writes `value` to `path` through a synced temporary file that replaces the old one, so a crash
+/// at any point leaves either the old or the new value on disk.
+pub(crate) fn save<T, E>(path: &Path, value: &T) -> Result<(), E>
+where
+    T: Serialize,
+    E: From<std::io::Error> + From<serde_json::Error>,
+{
+    let dir = parent(path);
+    std::fs::create_dir_all(dir)?;
+    let mut file = tempfile::NamedTempFile::new_in(dir)?;
+    serde_json::to_writer_pretty(&mut file, value)?;
+    file.write_all(b"\n")?;
+    file.as_file().sync_all()?;
+    file.persist(path).map_err(|e| e.error)?;
+    Ok(())
+}
+
+/// Reads the value at `path`, applies `change` and saves the result if it succeeds, holding an
+/// exclusive lock on `<path>.lock` throughout so that concurrent updates apply one after the other
+/// instead of overwriting each other.
+pub(crate) fn update<T, R, E>(path: &Path, change: impl FnOnce(&mut T) -> Result<R, E>) -> Result<R, E>
+where
+    T: Serialize + DeserializeOwned + Default,
+    E: From<std::io::Error> + From<serde_json::Error>,
+{
+    std::fs::create_dir_all(parent(path))?;
+    let lock = OpenOptions::new().create(true).truncate(false).write(true).open(lock_path(path))?;
+    lock.lock()?;
+    let mut value = load::<T, E>(path)?;
+    let result = change(&mut value)?;
+    save::<T, E>(path, &value)?;
+    lock.unlock()?;
+    Ok(result)
+}
+
+fn parent(path: &Path) -> &Path {
+    path.parent().filter(|dir| !dir.as_os_str().is_empty()).unwrap_or(Path::new("."))
+}
+
+/// The lock file is left in place: removing it would let a waiting process lock a file that a newer
+/// one has already replaced.
+fn lock_path(path: &Path) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(".lock");
+    PathBuf::from(name)
+}
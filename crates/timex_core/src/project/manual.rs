@@ -0,0 +1,413 @@
+use super::{EntrySource, Project, TimeEntry};
+use chrono::{DateTime, Days, NaiveDate, NaiveTime, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+#[derive(Debug, thiserror::Error)]
+pub enum ManualEntriesError {
+    #[error("Failed to read manual entries: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Failed to parse TOML: {0}")]
+    Toml(#[from] toml::de::Error),
+    #[error("Failed to parse JSON: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("{entry}: no project with code '{project}'")]
+    UnknownProject { entry: String, project: String },
+    #[error("{entry}: give both a start and an end time, or neither")]
+    PartialTimes { entry: String },
+    #[error("{entry}: a start and end time are needed to add or replace time")]
+    MissingTimes { entry: String },
+    #[error("{entry}: start and end are both {time}; an entry cannot be empty")]
+    EmptyRange { entry: String, time: NaiveTime },
+    #[error("{entry}: {time} does not exist in the local timezone")]
+    NonexistentTime { entry: String, time: String },
+}
+
+/// What a manual entry does to the time recorded for its project.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Adjustment {
+    /// Records time on top of the inferred entries, e.g. a meeting.
+    #[default]
+    Add,
+    /// Records time in place of everything inferred for the project on that day.
+    Replace,
+    /// Drops the time inferred for the project between `start` and `end`, or on the whole day.
+    Delete,
+}
+
+/// One line of the manual entries file; times are local wall-clock times on `date`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ManualEntry {
+    #[serde(default)]
+    pub action: Adjustment,
+    pub date: NaiveDate,
+    /// The code of the project the entry is for.
+    pub project: String,
+    pub start: Option<NaiveTime>,
+    /// Ends on the next day when earlier than `start`.
+    pub end: Option<NaiveTime>,
+    #[serde(default)]
+    pub task: Option<String>,
+    /// Overrides the task's description, tags and billable flag where given.
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub tags: Option<Vec<String>>,
+    #[serde(default)]
+    pub billable: Option<bool>,
+}
+
+/// Time recorded by hand, kept in `entries.toml` or `entries.json` next to `workload.toml`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ManualEntries {
+    #[serde(default)]
+    pub entries: Vec<ManualEntry>,
+    /// The file name entries are labelled with in their provenance, e.g. `entries.toml#2`.
+    #[serde(skip)]
+    pub name: String,
+}
+
+impl ManualEntries {
+    /// Reads the entries at `path`, as JSON if it ends in `.json` and as TOML otherwise. A missing
+    /// file holds no entries.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ManualEntriesError> {
+        let path = path.as_ref();
+        let content = match std::fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(e.into()),
+        };
+        let mut entries: ManualEntries = match path.extension().is_some_and(|extension| extension == "json") {
+            true if content.trim().is_empty() => ManualEntries::default(),
+            true => serde_json::from_str(&content)?,
+            false => toml::from_str(&content)?,
+        };
+        entries.name = path.file_name().map_or_else(String::new, |name| name.to_string_lossy().into_owned());
+        Ok(entries)
+    }
+
+    /// Checks that every entry names one of `projects`, has the times its action needs and does not
+    /// start and end at the same time.
+    pub fn validate(&self, projects: &[Project]) -> Result<(), ManualEntriesError> {
+        for (index, entry) in self.entries.iter().enumerate() {
+            if !projects.iter().any(|project| project.code == entry.project) {
+                return Err(ManualEntriesError::UnknownProject {
+                    entry: self.label(index),
+                    project: entry.project.clone(),
+                });
+            }
+            if entry.start.is_some() != entry.end.is_some() {
+                return Err(ManualEntriesError::PartialTimes { entry: self.label(index) });
+            }
+            if entry.action != Adjustment::Delete && entry.start.is_none() {
+                return Err(ManualEntriesError::MissingTimes { entry: self.label(index) });
+            }
+            if let (Some(start), Some(end)) = (entry.start, entry.end) {
+                if start == end {
+                    return Err(ManualEntriesError::EmptyRange {
+                        entry: self.label(index),
+                        time: start,
+                    });
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Applies the entries for `project` to its `inferred` entries, in file order, with local
+    /// times in `tz`.
+    ///
+    /// Every manual entry first clears its range: `replace` drops the inferred time of the whole
+    /// day, `delete` that of its range or day, and `add` and `replace` then cut whatever they
+    /// overlap, earlier manual entries included, before being inserted. Later entries therefore
+    /// win over earlier ones and manual time over inferred time. Entries that are cut keep the
+    /// pieces outside the range and list the manual entry in [`super::Provenance::adjusted_by`].
+    /// The result is ordered by start and end time.
+    pub fn merge<Tz: TimeZone>(
+        &self,
+        project: &Project,
+        inferred: Vec<TimeEntry>,
+        tz: &Tz,
+    ) -> Result<Vec<TimeEntry>, ManualEntriesError> {
+        let mut entries = inferred;
+        for (index, manual) in self.entries.iter().enumerate() {
+            if manual.project != project.code {
+                continue;
+            }
+            let label = self.label(index);
+            let day = (
+                local(tz, manual.date, NaiveTime::MIN, &label)?,
+                local(tz, manual.date + Days::new(1), NaiveTime::MIN, &label)?,
+            );
+            let range = match (manual.start, manual.end) {
+                (Some(start), Some(end)) => {
+                    let end_date = if end < start { manual.date + Days::new(1) } else { manual.date };
+                    Some((local(tz, manual.date, start, &label)?, local(tz, end_date, end, &label)?))
+                }
+                (None, None) => None,
+                _ => return Err(ManualEntriesError::PartialTimes { entry: label }),
+            };
+
+            let is_inferred = |entry: &TimeEntry| entry.source == EntrySource::Inferred;
+            match manual.action {
+                Adjustment::Add => {}
+                Adjustment::Replace => cut(&mut entries, day, is_inferred, &label),
+                Adjustment::Delete => {
+                    cut(&mut entries, range.unwrap_or(day), is_inferred, &label);
+                    continue;
+                }
+            }
+            let (start, end) = range.ok_or_else(|| ManualEntriesError::MissingTimes { entry: label.clone() })?;
+            insert(&mut entries, manual.to_entry(project, start, end, label));
+        }
+
+        entries.sort_by_key(|entry| (entry.start_time, entry.end_time));
+        Ok(entries)
+    }
+
+    fn label(&self, index: usize) -> String {
+        format!("{}#{}", self.name, index + 1)
+    }
+}
+
+impl ManualEntry {
+    fn to_entry(&self, project: &Project, start: DateTime<Utc>, end: DateTime<Utc>, label: String) -> TimeEntry {
+        let mut entry = project.time_entry(self.task.clone(), start, end, EntrySource::Manual);
+        if let Some(description) = &self.description {
+            entry.description = description.clone();
+        }
+        if let Some(tags) = &self.tags {
+            entry.tags = tags.clone();
+        }
+        if let Some(billable) = self.billable {
+            entry.billable = billable;
+        }
+        entry.provenance.origin = Some(label);
+        entry
+    }
+}
+
+/// Adds `recorded` entries, e.g. stopped timers, to `entries` in order, each cutting what it
+/// overlaps like a manual `add` entry, and orders the result by start and end time.
+pub fn overlay(mut entries: Vec<TimeEntry>, recorded: impl IntoIterator<Item = TimeEntry>) -> Vec<TimeEntry> {
+    for entry in recorded {
+        insert(&mut entries, entry);
+    }
+    entries.sort_by_key(|entry| (entry.start_time, entry.end_time));
+    entries
+}
+
+/// Inserts `entry` after cutting its span out of every other entry, crediting the cut to its origin.
+fn insert(entries: &mut Vec<TimeEntry>, entry: TimeEntry) {
+    let label = entry.provenance.origin.clone().unwrap_or_else(|| entry.source.to_string());
+    cut(entries, (entry.start_time, entry.end_time), |_| true, &label);
+    entries.push(entry);
+}
+
+/// The UTC instant of the local `time` on `date`, taking the earlier one when clocks go back.
+fn local<Tz: TimeZone>(
+    tz: &Tz,
+    date: NaiveDate,
+    time: NaiveTime,
+    label: &str,
+) -> Result<DateTime<Utc>, ManualEntriesError> {
+    let datetime = date.and_time(time);
+    tz.from_local_datetime(&datetime)
+        .earliest()
+        .map(|datetime| datetime.to_utc())
+        .ok_or_else(|| ManualEntriesError::NonexistentTime {
+            entry: label.to_string(),
+            time: datetime.to_string(),
+        })
+}
+
+/// Removes the part of every entry selected by `filter` that lies within `range`, keeping what
+/// is left before and after it.
+fn cut(
+    entries: &mut Vec<TimeEntry>,
+    (start, end): (DateTime<Utc>, DateTime<Utc>),
+    filter: impl Fn(&TimeEntry) -> bool,
+    label: &str,
+) {
+    let mut kept = Vec::with_capacity(entries.len());
+    for entry in entries.drain(..) {
+        if !filter(&entry) || entry.start_time >= end || entry.end_time <= start {
+            kept.push(entry);
+            continue;
+        }
+        let mut pieces = Vec::new();
+        if entry.start_time < start {
+            pieces.push((entry.start_time, start));
+        }
+        if entry.end_time > end {
+            pieces.push((end, entry.end_time));
+        }
+        for (start_time, end_time) in pieces {
+            let mut piece = TimeEntry {
+                start_time,
+                end_time,
+                ..entry.clone()
+            };
+            piece.provenance.adjusted_by.push(label.to_string());
+            kept.push(piece);
+        }
+    }
+    *entries = kept;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn project() -> Project {
+        Project::for_test("P1", "/tmp/project1")
+    }
+
+    fn at(day: u32, hour: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 1, day, hour, 0, 0).unwrap()
+    }
+
+    fn inferred(day: u32, start_hour: u32, end_hour: u32) -> TimeEntry {
+        project().time_entry(None, at(day, start_hour), at(day, end_hour), EntrySource::Inferred)
+    }
+
+    fn manual(content: &str) -> ManualEntries {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("entries.toml");
+        std::fs::write(&path, content).unwrap();
+        ManualEntries::load(path).unwrap()
+    }
+
+    #[test]
+    fn test_manual_entries_cut_replace_and_delete() {
+        let manual = manual(
+            r#"
+            [[entries]]
+            date = "2024-01-01"
+            project = "P1"
+            start = "10:00"
+            end = "11:00"
+            description = "Sprint planning"
+            tags = ["meeting"]
+
+            [[entries]]
+            action = "replace"
+            date = "2024-01-02"
+            project = "P1"
+            start = "13:00"
+            end = "14:00"
+
+            [[entries]]
+            action = "delete"
+            date = "2024-01-03"
+            project = "P1"
+            start = "09:00"
+            end = "10:00"
+
+            [[entries]]
+            action = "delete"
+            date = "2024-01-04"
+            project = "OTHER"
+        "#,
+        );
+        let entries = vec![
+            inferred(3, 8, 12),
+            inferred(2, 9, 12),
+            inferred(1, 9, 12),
+            inferred(4, 9, 10),
+        ];
+        let merged = manual.merge(&project(), entries, &Utc).unwrap();
+        let spans: Vec<_> = merged
+            .iter()
+            .map(|entry| (entry.start_time, entry.end_time, entry.source, entry.provenance.to_string()))
+            .collect();
+
+        let adjusted = |n: u32| format!("adjusted by entries.toml#{n}");
+        assert_eq!(
+            spans,
+            [
+                (at(1, 9), at(1, 10), EntrySource::Inferred, adjusted(1)),
+                (at(1, 10), at(1, 11), EntrySource::Manual, "entries.toml#1".to_string()),
+                (at(1, 11), at(1, 12), EntrySource::Inferred, adjusted(1)),
+                (at(2, 13), at(2, 14), EntrySource::Manual, "entries.toml#2".to_string()),
+                (at(3, 8), at(3, 9), EntrySource::Inferred, adjusted(3)),
+                (at(3, 10), at(3, 12), EntrySource::Inferred, adjusted(3)),
+                (at(4, 9), at(4, 10), EntrySource::Inferred, String::new()),
+            ]
+        );
+        assert_eq!(merged[1].description, "Sprint planning");
+        assert_eq!(merged[1].tags, ["meeting"]);
+    }
+
+    #[test]
+    fn test_later_manual_entries_win() {
+        let manual = manual(
+            r#"
+            [[entries]]
+            date = "2024-01-01"
+            project = "P1"
+            start = "22:00"
+            end = "02:00"
+            task = "ABC-1"
+
+            [[entries]]
+            date = "2024-01-02"
+            project = "P1"
+            start = "01:00"
+            end = "03:00"
+            task = "ABC-2"
+        "#,
+        );
+        let merged = manual.merge(&project(), Vec::new(), &Utc).unwrap();
+        let spans: Vec<_> = merged
+            .iter()
+            .map(|entry| (entry.start_time, entry.end_time, entry.task.as_deref()))
+            .collect();
+
+        assert_eq!(
+            spans,
+            [(at(1, 22), at(2, 1), Some("ABC-1")), (at(2, 1), at(2, 3), Some("ABC-2"))]
+        );
+        assert_eq!(merged[0].provenance.adjusted_by, ["entries.toml#2"]);
+    }
+
+    #[test]
+    fn test_validation_names_the_entry() {
+        let manual = manual("[[entries]]\ndate = \"2024-01-01\"\nproject = \"P1\"\n");
+        let error = manual.validate(&[project()]).unwrap_err();
+
+        assert_eq!(error.to_string(), "entries.toml#1: a start and end time are needed to add or replace time");
+        assert_eq!(ManualEntries::load("/nonexistent/entries.json").unwrap().entries, []);
+    }
+
+    #[test]
+    fn test_delete_with_one_time_is_rejected() {
+        let manual = manual(
+            "[[entries]]\naction = \"delete\"\ndate = \"2024-01-01\"\nproject = \"P1\"\nstart = \"09:00\"\n",
+        );
+
+        assert_eq!(
+            manual.validate(&[project()]).unwrap_err().to_string(),
+            "entries.toml#1: give both a start and an end time, or neither"
+        );
+        // Unvalidated, the day's inferred time is kept instead of deleted wholesale.
+        assert!(matches!(
+            manual.merge(&project(), vec![inferred(1, 9, 12)], &Utc),
+            Err(ManualEntriesError::PartialTimes { .. })
+        ));
+    }
+
+    #[test]
+    fn test_equal_start_and_end_are_rejected() {
+        let manual = manual("[[entries]]\ndate = \"2024-01-01\"\nproject = \"P1\"\nstart = \"09:00\"\nend = \"09:00\"\n");
+
+        assert!(matches!(
+            manual.validate(&[project()]),
+            Err(ManualEntriesError::EmptyRange { .. })
+        ));
+        // Unvalidated, the entry stays empty rather than running for a whole day.
+        let merged = manual.merge(&project(), Vec::new(), &Utc).unwrap();
+        assert_eq!((merged[0].start_time, merged[0].end_time), (at(1, 9), at(1, 9)));
+    }
+}
@@ -1,6 +1,16 @@
+pub mod inference;
+mod json_file;
+pub mod manual;
+pub mod sync;
+pub mod tickets;
+pub mod timer;
 pub mod workload;
 
-use chrono::{DateTime, Utc};
+use crate::git::diff::DiffOptions;
+use crate::git::identity::Identity;
+use crate::git::paths::PathFilter;
+use chrono::{DateTime, FixedOffset, Local, NaiveDate, Utc};
+use tickets::TicketPatterns;
 use serde::{Deserialize, Serialize};
 use thiserror;
 use toml;
@@ -18,19 +28,210 @@ pub enum ParseWorkloadError {
 
 
 
-#[derive(Debug, Serialize, Deserialize, Hash, Eq, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, Hash, Eq, PartialEq)]
 pub struct Project {
     pub name: String,
     pub code: String,
     pub description: String,
     pub git_url: String,
+    /// Globs selecting the files whose changes count, e.g. `["src/**"]`; empty means every file.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub include: Vec<String>,
+    /// Globs for files to leave out of diffs, line counts and summaries, e.g. `["Cargo.lock", "vendor/"]`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub exclude: Vec<String>,
+    /// Regexes that find ticket IDs in branch names, commit titles and trailers, e.g. `['[A-Z]{2,}-\d+']`.
+    #[serde(default, skip_serializing_if = "TicketPatterns::is_empty")]
+    pub tickets: TicketPatterns,
+    /// Whether time on the project is billed, unless a task says otherwise.
+    #[serde(default = "billable_by_default")]
+    pub billable: bool,
+    /// Known tasks, e.g. tickets, with the details their time entries carry.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tasks: Vec<Task>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+/// A unit of work within a project that time is booked against, usually a ticket.
+#[derive(Debug, Clone, Serialize, Deserialize, Hash, Eq, PartialEq)]
+pub struct Task {
+    /// The ticket ID or other code the task is referenced by, e.g. `ABC-123`.
+    pub code: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub description: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    #[serde(default = "billable_by_default")]
+    pub billable: bool,
+}
+
+fn billable_by_default() -> bool {
+    true
+}
+
+fn full_confidence() -> f64 {
+    1.0
+}
+
+impl Project {
+    /// The configured task with `code`, if any.
+    pub fn task(&self, code: &str) -> Option<&Task> {
+        self.tasks.iter().find(|task| task.code == code)
+    }
+
+    /// An entry for time spent on the project and, if given, `task`, carrying the task's
+    /// description, tags and billable flag where it is configured.
+    pub fn time_entry(
+        &self,
+        task: Option<String>,
+        start_time: DateTime<Utc>,
+        end_time: DateTime<Utc>,
+        source: EntrySource,
+    ) -> TimeEntry {
+        let details = task.as_deref().and_then(|code| self.task(code));
+        TimeEntry {
+            project: self.code.clone(),
+            start_time,
+            end_time,
+            utc_offset: None,
+            description: details.map(|task| task.description.clone()).unwrap_or_default(),
+            tags: details.map(|task| task.tags.clone()).unwrap_or_default(),
+            billable: details.map_or(self.billable, |task| task.billable),
+            task,
+            source,
+            confidence: full_confidence(),
+            provenance: Provenance::default(),
+        }
+    }
+
+    /// A project on the repository at `git_url` with every optional setting left at its default.
+    #[cfg(test)]
+    pub(crate) fn for_test(code: &str, git_url: &str) -> Project {
+        Project {
+            name: format!("Project {code}"),
+            code: code.into(),
+            description: String::new(),
+            git_url: git_url.into(),
+            include: Vec::new(),
+            exclude: Vec::new(),
+            tickets: TicketPatterns::default(),
+            billable: true,
+            tasks: Vec::new(),
+        }
+    }
+
+    /// Diff options restricted to the files selected by [`Project::include`] and [`Project::exclude`].
+    pub fn diff_options(&self) -> DiffOptions {
+        DiffOptions {
+            paths: PathFilter::new(&self.include, &self.exclude),
+            ..DiffOptions::default()
+        }
+    }
+}
+
+/// Where a time entry came from.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EntrySource {
+    /// Worked out from commit history.
+    #[default]
+    Inferred,
+    /// Recorded by the user.
+    Manual,
+    /// Read from another time tracking system.
+    Imported,
+}
+
+impl std::fmt::Display for EntrySource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            EntrySource::Inferred => "inferred",
+            EntrySource::Manual => "manual",
+            EntrySource::Imported => "imported",
+        })
+    }
+}
+
+/// A span of time spent on a project, referencing the project and task by code.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct TimeEntry {
-    pub project: Project,
+    /// The code of the project the time was spent on.
+    pub project: String,
+    /// The code of the task, e.g. the ticket `ABC-123`, if the work could be tied to one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub task: Option<String>,
     pub start_time: DateTime<Utc>,
     pub end_time: DateTime<Utc>,
+    /// The UTC offset, in seconds east, the work was done at, e.g. that of the author of the
+    /// commits the entry was inferred from; `None` means the local timezone of whoever reads it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub utc_offset: Option<i32>,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub description: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    #[serde(default = "billable_by_default")]
+    pub billable: bool,
+    #[serde(default)]
+    pub source: EntrySource,
+    /// How sure the source is that the time was worked as recorded, from 0 to 1.
+    #[serde(default = "full_confidence")]
+    pub confidence: f64,
+    #[serde(default, skip_serializing_if = "Provenance::is_empty")]
+    pub provenance: Provenance,
+}
+
+/// Which records an entry was read from or changed by, so merged timesheets can be traced back.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Provenance {
+    /// The record the entry was read from, e.g. `entries.toml#3` for the third manual entry.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub origin: Option<String>,
+    /// The manual entries that cut the entry short or split it, in the order they were applied.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub adjusted_by: Vec<String>,
+}
+
+impl Provenance {
+    pub fn is_empty(&self) -> bool {
+        self.origin.is_none() && self.adjusted_by.is_empty()
+    }
+}
+
+impl std::fmt::Display for Provenance {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let adjusted = (!self.adjusted_by.is_empty()).then(|| format!("adjusted by {}", self.adjusted_by.join(", ")));
+        let parts: Vec<String> = self.origin.iter().cloned().chain(adjusted).collect();
+        f.write_str(&parts.join("; "))
+    }
+}
+
+impl TimeEntry {
+    pub fn duration(&self) -> chrono::Duration {
+        self.end_time - self.start_time
+    }
+
+    /// When the entry starts, in the timezone the work was done in.
+    pub fn local_start(&self) -> DateTime<FixedOffset> {
+        self.in_work_timezone(self.start_time)
+    }
+
+    /// When the entry ends, in the timezone the work was done in.
+    pub fn local_end(&self) -> DateTime<FixedOffset> {
+        self.in_work_timezone(self.end_time)
+    }
+
+    /// The calendar day the entry starts on where the work was done, which for inferred entries
+    /// is the author's local date rather than that of the machine reading it.
+    pub fn work_day(&self) -> NaiveDate {
+        self.local_start().date_naive()
+    }
+
+    fn in_work_timezone(&self, time: DateTime<Utc>) -> DateTime<FixedOffset> {
+        match self.utc_offset.and_then(FixedOffset::east_opt) {
+            Some(offset) => time.with_timezone(&offset),
+            None => time.with_timezone(&Local).fixed_offset(),
+        }
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -43,6 +244,25 @@ pub struct WorkConfig {
 pub struct UserConfig {
     pub name: String,
     pub email: String,
+    /// Additional emails the user commits under, e.g. work and personal addresses.
+    #[serde(default)]
+    pub emails: Vec<String>,
+    /// Additional names the user commits under.
+    #[serde(default)]
+    pub aliases: Vec<String>,
+    /// Also count commits that list the user in a `Co-authored-by` trailer as the user's own.
+    #[serde(default)]
+    pub co_authors: bool,
+}
+
+impl UserConfig {
+    /// The identity used to pick the user's own commits out of a repository.
+    pub fn identity(&self) -> Identity {
+        Identity {
+            names: std::iter::once(&self.name).chain(&self.aliases).cloned().collect(),
+            emails: std::iter::once(&self.email).chain(&self.emails).cloned().collect(),
+        }
+    }
 }
 
 #[cfg(test)]
@@ -79,6 +299,60 @@ mod tests {
         assert_eq!(workload.projects[0].code, "P1");
     }
 
+    #[test]
+    fn test_user_identity_includes_aliases() {
+        let config_content = r#"
+            [user]
+            name = "John Doe"
+            email = "john@example.com"
+            emails = ["jd@work.example"]
+            aliases = ["jdoe"]
+
+            [[projects]]
+            name = "Project 1"
+            code = "P1"
+            description = "Test Project 1"
+            git_url = "https://github.com/test/project1"
+        "#;
+
+        let mut temp_file = NamedTempFile::new().unwrap();
+        write!(temp_file, "{}", config_content).unwrap();
+
+        let workload = Workload::from_toml_file(temp_file.path().to_str().unwrap()).unwrap();
+        let identity = workload.user.identity();
+
+        assert_eq!(identity.names, vec!["John Doe", "jdoe"]);
+        assert_eq!(identity.emails, vec!["john@example.com", "jd@work.example"]);
+    }
+
+    #[test]
+    fn test_entries_take_details_from_tasks() {
+        let project: Project = toml::from_str(
+            r#"
+            name = "Project 1"
+            code = "P1"
+            description = "Test Project 1"
+            git_url = "/tmp/project1"
+            billable = false
+
+            [[tasks]]
+            code = "ABC-1"
+            description = "Login page"
+            tags = ["frontend"]
+        "#,
+        )
+        .unwrap();
+        let time = Utc::now();
+
+        let entry = project.time_entry(Some("ABC-1".into()), time, time, EntrySource::Manual);
+        assert_eq!((entry.project.as_str(), entry.description.as_str()), ("P1", "Login page"));
+        assert_eq!(entry.tags, ["frontend"]);
+        assert!(entry.billable);
+
+        let untracked = project.time_entry(Some("ABC-2".into()), time, time, EntrySource::Inferred);
+        assert!(!untracked.billable && untracked.tags.is_empty());
+    }
+
     #[test]
     fn test_workload_from_invalid_toml() {
         let invalid_content = r#"
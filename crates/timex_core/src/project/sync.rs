@@ -0,0 +1,234 @@
+use crate::git::diff::is_ancestor;
+use crate::git::error::GitError;
+use super::json_file;
+use crate::summary::ProjectActivity;
+use gix::hash::ObjectId;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+#[derive(Debug, thiserror::Error)]
+pub enum SyncStateError {
+    #[error("Failed to access sync state: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Failed to parse sync state: {0}")]
+    Json(#[from] serde_json::Error),
+}
+
+/// The last processed tip of every ref, per project code, as kept between runs.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SyncState {
+    #[serde(default)]
+    pub projects: BTreeMap<String, BTreeMap<String, String>>,
+}
+
+/// How a ref moved since the last sync.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RefUpdate {
+    /// The ref did not exist at the last sync.
+    Created,
+    /// The new tip descends from the old one.
+    FastForward,
+    /// The old tip is no longer part of the ref's history, e.g. after a rebase and force-push.
+    ForcePushed,
+    /// The ref no longer exists.
+    Deleted,
+}
+
+/// The outcome of syncing one project.
+#[derive(Debug)]
+pub struct ProjectSync {
+    /// The user's commits that no earlier sync processed, and the diffs they introduced.
+    pub activity: ProjectActivity,
+    /// Every ref whose tip changed, by full name.
+    pub updates: BTreeMap<String, RefUpdate>,
+}
+
+impl SyncState {
+    /// Reads the state at `path`, starting afresh if the file does not exist yet.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, SyncStateError> {
+        json_file::load(path.as_ref())
+    }
+
+    /// Writes the state to `path`, replacing the previous file atomically so that an interrupted
+    /// run leaves the old state intact.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), SyncStateError> {
+        json_file::save(path.as_ref(), self)
+    }
+
+    /// Drops what is known about `project_code`, so that its next sync walks the full history.
+    pub fn forget(&mut self, project_code: &str) {
+        self.projects.remove(project_code);
+    }
+
+    /// What is known about `project_code` alone, so that projects can be synced on separate
+    /// threads and [absorbed](SyncState::absorb) back afterwards.
+    pub(crate) fn for_project(&self, project_code: &str) -> SyncState {
+        let known = self.projects.get_key_value(project_code);
+        SyncState {
+            projects: known.map(|(code, refs)| (code.clone(), refs.clone())).into_iter().collect(),
+        }
+    }
+
+    /// Takes over everything `other` knows, replacing what is known about the same projects.
+    pub(crate) fn absorb(&mut self, other: SyncState) {
+        self.projects.extend(other.projects);
+    }
+
+    /// The tips processed by the last sync of `project_code` that still exist in `repo`.
+    ///
+    /// Tips that were garbage collected since cannot hide anything, so their ancestry is walked
+    /// again; storing the same commits twice is harmless.
+    pub(crate) fn known_tips(&self, repo: &gix::Repository, project_code: &str) -> Vec<ObjectId> {
+        self.projects
+            .get(project_code)
+            .into_iter()
+            .flat_map(|refs| refs.values())
+            .filter_map(|hex| ObjectId::from_hex(hex.as_bytes()).ok())
+            .filter(|id| repo.find_commit(*id).is_ok())
+            .collect()
+    }
+}
+
+/// Compares the refs recorded for `project_code` with the current `tips`, and records the latter.
+///
+/// Force-pushed refs need no special treatment in the walk: hiding the old tip only hides history
+/// that is still shared with the new one, and the abandoned commits stay in the store as work done.
+pub(crate) fn update_refs(
+    repo: &gix::Repository,
+    state: &mut SyncState,
+    project_code: &str,
+    tips: &BTreeMap<String, ObjectId>,
+) -> Result<BTreeMap<String, RefUpdate>, GitError> {
+    let previous = state.projects.remove(project_code).unwrap_or_default();
+    let mut updates = BTreeMap::new();
+
+    for (name, tip) in tips {
+        let update = match previous.get(name).map(|hex| ObjectId::from_hex(hex.as_bytes())) {
+            None => Some(RefUpdate::Created),
+            Some(Ok(old)) if old == *tip => None,
+            Some(Ok(old)) if repo.find_commit(old).is_ok() && is_ancestor(repo, old, *tip)? => {
+                Some(RefUpdate::FastForward)
+            }
+            Some(_) => Some(RefUpdate::ForcePushed),
+        };
+        if let Some(update) = update {
+            updates.insert(name.clone(), update);
+        }
+    }
+    for name in previous.keys().filter(|name| !tips.contains_key(*name)) {
+        updates.insert(name.clone(), RefUpdate::Deleted);
+    }
+
+    state.projects.insert(
+        project_code.to_string(),
+        tips.iter().map(|(name, tip)| (name.clone(), tip.to_string())).collect(),
+    );
+    Ok(updates)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git::test_repo::TestRepo;
+    use crate::project::workload::Workload;
+    use crate::project::Project;
+    use crate::store::Store;
+
+    fn workload(repo: &TestRepo) -> Workload {
+        Workload::for_test(vec![Project::for_test("T1", repo.path().to_str().unwrap())])
+    }
+
+    fn titles(sync: &ProjectSync) -> Vec<&str> {
+        sync.activity.commits.iter().map(|commit| commit.title.as_str()).collect()
+    }
+
+    #[test]
+    fn test_second_sync_only_sees_new_commits() {
+        let repo = TestRepo::new();
+        repo.commit("a.txt", "1\n", "one");
+        repo.commit("a.txt", "2\n", "two");
+        let workload = workload(&repo);
+        let project = &workload.projects[0];
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("state.json");
+
+        let mut state = SyncState::load(&path).unwrap();
+        let first = workload.sync_project(project, &mut state).unwrap();
+        assert_eq!(titles(&first), ["two", "one"]);
+        assert_eq!(first.updates["refs/heads/main"], RefUpdate::Created);
+        state.save(&path).unwrap();
+
+        repo.commit("a.txt", "3\n", "three");
+        let mut state = SyncState::load(&path).unwrap();
+        let second = workload.sync_project(project, &mut state).unwrap();
+        assert_eq!(titles(&second), ["three"]);
+        assert_eq!(second.updates["refs/heads/main"], RefUpdate::FastForward);
+
+        let third = workload.sync_project(project, &mut state).unwrap();
+        assert!(third.activity.commits.is_empty());
+        assert!(third.updates.is_empty());
+    }
+
+    #[test]
+    fn test_incremental_activity_takes_older_commits_from_the_store() {
+        let repo = TestRepo::new();
+        repo.commit("a.txt", "1\n", "one");
+        let workload = workload(&repo);
+        let project = &workload.projects[0];
+        let store = Store::open_in_memory().unwrap();
+        let options = workload.walk_options();
+        let mut state = SyncState::default();
+
+        workload.incremental_activity(project, &options, &mut state, &store).unwrap();
+        repo.commit("a.txt", "2\n", "two");
+        let activity = workload.incremental_activity(project, &options, &mut state, &store).unwrap();
+
+        let titles: Vec<&str> = activity.commits.iter().map(|commit| commit.title.as_str()).collect();
+        assert_eq!(titles, ["two", "one"]);
+        // Only the new commit was diffed; the older one comes from the store without hunks.
+        let hunks: Vec<usize> = activity.diffs.iter().map(|diff| diff.changes[0].hunks.len()).collect();
+        assert_eq!(hunks, [1, 0]);
+
+        let later = workload.walk_options().with_since(chrono::Utc::now() + chrono::Duration::hours(1));
+        assert!(workload.incremental_activity(project, &later, &mut state, &store).unwrap().commits.is_empty());
+    }
+
+    #[test]
+    fn test_generate_workload_saves_the_processed_tips() {
+        let repo = TestRepo::new();
+        repo.commit("a.txt", "1\n", "one");
+        let workload = workload(&repo);
+        let store = Store::open_in_memory().unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("state.json");
+
+        let first = workload.generate_workload(&path, &store).unwrap();
+        assert!(SyncState::load(&path).unwrap().projects.contains_key("T1"));
+        repo.commit("a.txt", "2\n", "two");
+        let second = workload.generate_workload(&path, &store).unwrap();
+
+        let name = &workload.projects[0].name;
+        assert_eq!((first[name].len(), second[name].len()), (1, 2));
+    }
+
+    #[test]
+    fn test_force_push_is_detected() {
+        let repo = TestRepo::new();
+        repo.commit("a.txt", "1\n", "one");
+        repo.commit("a.txt", "2\n", "two");
+        let workload = workload(&repo);
+        let project = &workload.projects[0];
+
+        let mut state = SyncState::default();
+        workload.sync_project(project, &mut state).unwrap();
+
+        repo.git(&["reset", "--hard", "HEAD~1"]);
+        repo.commit("a.txt", "2b\n", "two, rewritten");
+        let sync = workload.sync_project(project, &mut state).unwrap();
+
+        assert_eq!(titles(&sync), ["two, rewritten"]);
+        assert_eq!(sync.updates["refs/heads/main"], RefUpdate::ForcePushed);
+    }
+}
@@ -0,0 +1,200 @@
+use crate::git::diff::{named_reference_tips, CommitTreeIterator, WalkOptions};
+use crate::git::error::GitError;
+use crate::git::GitCommit;
+use gix::hash::ObjectId;
+use regex::Regex;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+
+/// Regular expressions that pull ticket IDs, e.g. `ABC-123`, out of branch names, commit titles
+/// and trailers.
+///
+/// A pattern with a capture group yields the first group, otherwise the whole match, so
+/// `#(\d+)` turns `Fix #42` into `42`. Invalid patterns are rejected when the workload is read.
+#[derive(Debug, Clone, Default)]
+pub struct TicketPatterns(Vec<Regex>);
+
+impl TicketPatterns {
+    pub fn new(patterns: &[&str]) -> Result<Self, regex::Error> {
+        patterns.iter().map(|pattern| Regex::new(pattern)).collect::<Result<_, _>>().map(TicketPatterns)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// The first ticket ID in `text`, trying the patterns in order.
+    pub fn find(&self, text: &str) -> Option<String> {
+        self.0.iter().find_map(|pattern| {
+            let captures = pattern.captures(text)?;
+            captures.get(1).or_else(|| captures.get(0)).map(|m| m.as_str().to_string())
+        })
+    }
+
+    /// The ticket `commit` was made for, from its title, else its `Refs` and other trailers.
+    pub fn for_commit(&self, commit: &GitCommit) -> Option<String> {
+        let trailers = &commit.trailers;
+        std::iter::once(commit.title.as_str())
+            .chain(trailers.refs.iter().map(String::as_str))
+            .chain(trailers.other.iter().map(|(_, value)| value.as_str()))
+            .find_map(|text| self.find(text))
+    }
+
+    /// Maps the commits committed between `since` and `until` to the ticket in the name of the
+    /// branch they were made on.
+    ///
+    /// Unmerged branches whose name holds a ticket claim the commits not reachable from branches
+    /// without one. Merged branches are only known from the titles git gives merge commits, e.g.
+    /// `Merge branch 'ABC-123-login'` or `Merge pull request #7 from user/ABC-123-login`, which
+    /// claim the commits the merge brought in. Only merges committed in the same window are looked
+    /// at, so a branch merged after `until` does not name its commits.
+    pub fn branch_tickets(
+        &self,
+        repo: &gix::Repository,
+        since: Option<chrono::DateTime<chrono::Utc>>,
+        until: Option<chrono::DateTime<chrono::Utc>>,
+    ) -> Result<HashMap<ObjectId, String>, GitError> {
+        let mut tickets = HashMap::new();
+        if self.is_empty() {
+            return Ok(tickets);
+        }
+        let window = WalkOptions {
+            since,
+            until,
+            ..WalkOptions::default()
+        };
+
+        let branches: Vec<(String, ObjectId)> = named_reference_tips(repo)?
+            .into_iter()
+            .filter_map(|(name, tip)| {
+                let short = name.strip_prefix("refs/heads/").or_else(|| name.strip_prefix("refs/remotes/"))?;
+                Some((short.to_string(), tip))
+            })
+            .collect();
+        let (ticket_branches, others): (Vec<_>, Vec<_>) =
+            branches.iter().partition(|(name, _)| self.find(name).is_some());
+        let others: Vec<ObjectId> = others.iter().map(|(_, tip)| *tip).collect();
+        for (name, tip) in ticket_branches {
+            let ticket = self.find(name).expect("partitioned on having a ticket");
+            let options = window.clone().with_revspec(tip.to_string()).with_hidden(others.clone());
+            claim(repo, options, &ticket, &mut tickets)?;
+        }
+
+        for id in CommitTreeIterator::with_options(repo, window.clone())? {
+            let commit = repo.find_commit(id?)?;
+            let parents: Vec<ObjectId> = commit.parent_ids().map(|id| id.detach()).collect();
+            let [first, merged, ..] = parents[..] else { continue };
+            let title = commit.message()?.title.to_string();
+            let Some(ticket) = merged_branch(&title).and_then(|branch| self.find(branch)) else {
+                continue;
+            };
+            let options = window.clone().with_revspec(merged.to_string()).with_hidden([first]);
+            claim(repo, options, &ticket, &mut tickets)?;
+        }
+        Ok(tickets)
+    }
+}
+
+/// Assigns `ticket` to the commits selected by `options` that have none yet.
+fn claim(
+    repo: &gix::Repository,
+    options: WalkOptions,
+    ticket: &str,
+    tickets: &mut HashMap<ObjectId, String>,
+) -> Result<(), GitError> {
+    for id in CommitTreeIterator::with_options(repo, options)? {
+        tickets.entry(id?).or_insert_with(|| ticket.to_string());
+    }
+    Ok(())
+}
+
+/// The branch named in a merge commit title written by git, GitHub or GitLab.
+fn merged_branch(title: &str) -> Option<&str> {
+    if let Some(rest) = title
+        .strip_prefix("Merge branch '")
+        .or_else(|| title.strip_prefix("Merge remote-tracking branch '"))
+    {
+        return rest.split_once('\'').map(|(branch, _)| branch);
+    }
+    let rest = title.strip_prefix("Merge pull request #")?;
+    let (_, from) = rest.split_once(" from ")?;
+    from.split_whitespace().next()
+}
+
+impl PartialEq for TicketPatterns {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.iter().map(Regex::as_str).eq(other.0.iter().map(Regex::as_str))
+    }
+}
+
+impl Eq for TicketPatterns {}
+
+impl Hash for TicketPatterns {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        for pattern in &self.0 {
+            pattern.as_str().hash(state);
+        }
+    }
+}
+
+impl Serialize for TicketPatterns {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.0.iter().map(Regex::as_str))
+    }
+}
+
+impl<'de> Deserialize<'de> for TicketPatterns {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let patterns = Vec::<String>::deserialize(deserializer)?;
+        let patterns: Vec<&str> = patterns.iter().map(String::as_str).collect();
+        TicketPatterns::new(&patterns).map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git::test_repo::TestRepo;
+
+    fn patterns() -> TicketPatterns {
+        TicketPatterns::new(&[r"\b[A-Z]{2,}-\d+\b", r"#(\d+)"]).unwrap()
+    }
+
+    #[test]
+    fn test_find_prefers_earlier_patterns_and_groups() {
+        let patterns = patterns();
+
+        assert_eq!(patterns.find("ABC-123: fix login").as_deref(), Some("ABC-123"));
+        assert_eq!(patterns.find("feature/ABC-9-search").as_deref(), Some("ABC-9"));
+        assert_eq!(patterns.find("Fix #42, see ABC-1").as_deref(), Some("ABC-1"));
+        assert_eq!(patterns.find("Fix #42").as_deref(), Some("42"));
+        assert_eq!(patterns.find("tidy up"), None);
+        assert_eq!(merged_branch("Merge pull request #7 from user/ABC-3-x"), Some("user/ABC-3-x"));
+    }
+
+    #[test]
+    fn test_branches_claim_their_commits() {
+        let repo = TestRepo::new();
+        repo.commit("a.txt", "1\n", "base");
+        repo.git(&["checkout", "-q", "-b", "ABC-1-merged"]);
+        let merged = repo.commit("b.txt", "1\n", "work on merged branch");
+        repo.git(&["checkout", "-q", "main"]);
+        repo.git(&["merge", "-q", "--no-ff", "ABC-1-merged", "-m", "Merge branch 'ABC-1-merged'"]);
+        repo.git(&["branch", "-q", "-D", "ABC-1-merged"]);
+        repo.git(&["checkout", "-q", "-b", "ABC-2-open"]);
+        let open = repo.commit("c.txt", "1\n", "work in progress");
+        repo.git(&["checkout", "-q", "main"]);
+        let untracked = repo.commit("a.txt", "2\n", "on main");
+
+        let tickets = patterns().branch_tickets(&repo.open(), None, None).unwrap();
+        let ticket = |hash: &str| tickets.get(&ObjectId::from_hex(hash.as_bytes()).unwrap()).map(String::as_str);
+
+        assert_eq!(ticket(&merged), Some("ABC-1"));
+        assert_eq!(ticket(&open), Some("ABC-2"));
+        assert_eq!(ticket(&untracked), None);
+
+        let later = chrono::Utc::now() + chrono::Duration::hours(1);
+        assert!(patterns().branch_tickets(&repo.open(), Some(later), None).unwrap().is_empty());
+    }
+}
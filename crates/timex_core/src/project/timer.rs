@@ -0,0 +1,221 @@
+use super::json_file;
+use super::{EntrySource, Project, TimeEntry};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+#[derive(Debug, thiserror::Error)]
+pub enum TimerError {
+    #[error("Failed to access timer state: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Failed to parse timer state: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("A timer is already running on {project}; stop it or switch instead")]
+    AlreadyRunning { project: String },
+    #[error("No timer is running")]
+    NotRunning,
+    #[error("The running timer is on '{0}', which is no longer a configured project")]
+    UnknownProject(String),
+}
+
+/// A timer that was started and not yet stopped.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RunningTimer {
+    /// The code of the project being timed.
+    pub project: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub task: Option<String>,
+    pub start_time: DateTime<Utc>,
+}
+
+/// The punch clock: the running timer, if any, and every stopped one as a [`TimeEntry`].
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TimerState {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub running: Option<RunningTimer>,
+    /// Stopped timers, oldest first.
+    #[serde(default)]
+    pub entries: Vec<TimeEntry>,
+}
+
+/// How a timer compares with the user's commits made while it ran.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TimerCheck {
+    /// Commits on the timed project.
+    pub on_project: usize,
+    /// Commits on other projects, by project code.
+    pub elsewhere: BTreeMap<String, usize>,
+    /// Commits on the timed project that name another ticket than the timer's task, by ticket.
+    pub other_tasks: BTreeMap<String, usize>,
+}
+
+impl TimerCheck {
+    /// Whether every commit made while the timer ran was for what was being timed.
+    pub fn agrees(&self) -> bool {
+        self.elsewhere.is_empty() && self.other_tasks.is_empty()
+    }
+}
+
+impl TimerState {
+    /// Reads the state at `path`, starting with no timers if the file does not exist yet.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, TimerError> {
+        json_file::load(path.as_ref())
+    }
+
+    /// Writes the state to `path` through a synced temporary file that replaces the old one, so a
+    /// crash at any point leaves either the old or the new state on disk.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), TimerError> {
+        json_file::save(path.as_ref(), self)
+    }
+
+    /// Re-reads the state at `path`, applies `change` and saves the result, locked against other
+    /// processes meanwhile so that timers started or stopped concurrently are never lost. Nothing
+    /// is saved if `change` fails.
+    pub fn update<T>(
+        path: impl AsRef<Path>,
+        change: impl FnOnce(&mut TimerState) -> Result<T, TimerError>,
+    ) -> Result<T, TimerError> {
+        json_file::update(path.as_ref(), change)
+    }
+
+    /// Starts timing `project` at `now`.
+    pub fn start(&mut self, project: &Project, task: Option<String>, now: DateTime<Utc>) -> Result<(), TimerError> {
+        if let Some(running) = &self.running {
+            return Err(TimerError::AlreadyRunning {
+                project: running.project.clone(),
+            });
+        }
+        self.running = Some(RunningTimer {
+            project: project.code.clone(),
+            task,
+            start_time: now,
+        });
+        Ok(())
+    }
+
+    /// Stops the running timer at `now` and records it as a manual entry on its project, one of
+    /// `projects`, whose provenance names it, e.g. `timer#3` for the third stopped timer.
+    pub fn stop(&mut self, projects: &[Project], now: DateTime<Utc>) -> Result<TimeEntry, TimerError> {
+        let running = self.running.as_ref().ok_or(TimerError::NotRunning)?;
+        let project = projects
+            .iter()
+            .find(|project| project.code == running.project)
+            .ok_or_else(|| TimerError::UnknownProject(running.project.clone()))?;
+
+        let mut entry = project.time_entry(running.task.clone(), running.start_time, now, EntrySource::Manual);
+        entry.provenance.origin = Some(format!("timer#{}", self.entries.len() + 1));
+        self.entries.push(entry.clone());
+        self.running = None;
+        Ok(entry)
+    }
+
+    /// Stops the running timer and starts timing `project` at the same instant.
+    pub fn switch(
+        &mut self,
+        projects: &[Project],
+        project: &Project,
+        task: Option<String>,
+        now: DateTime<Utc>,
+    ) -> Result<TimeEntry, TimerError> {
+        let entry = self.stop(projects, now)?;
+        self.start(project, task, now)?;
+        Ok(entry)
+    }
+
+    /// The stopped timers on the project with `project_code`.
+    pub fn project_entries<'a>(&'a self, project_code: &'a str) -> impl Iterator<Item = &'a TimeEntry> + 'a {
+        self.entries.iter().filter(move |entry| entry.project == project_code)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn project(code: &str) -> Project {
+        Project::for_test(code, "/tmp/project")
+    }
+
+    fn at(hour: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 1, 1, hour, 0, 0).unwrap()
+    }
+
+    #[test]
+    fn test_start_switch_stop() {
+        let projects = [project("P1"), project("P2")];
+        let mut state = TimerState::default();
+
+        assert!(matches!(state.stop(&projects, at(9)), Err(TimerError::NotRunning)));
+        state.start(&projects[0], Some("ABC-1".into()), at(9)).unwrap();
+        assert!(matches!(
+            state.start(&projects[1], None, at(10)),
+            Err(TimerError::AlreadyRunning { .. })
+        ));
+
+        let first = state.switch(&projects, &projects[1], None, at(11)).unwrap();
+        let second = state.stop(&projects, at(12)).unwrap();
+
+        assert_eq!((first.project.as_str(), first.task.as_deref()), ("P1", Some("ABC-1")));
+        assert_eq!((first.start_time, first.end_time, first.source), (at(9), at(11), EntrySource::Manual));
+        assert_eq!((second.start_time, second.end_time), (at(11), at(12)));
+        assert_eq!(second.provenance.origin.as_deref(), Some("timer#2"));
+        assert_eq!(state.running, None);
+        assert_eq!(state.project_entries("P2").count(), 1);
+    }
+
+    #[test]
+    fn test_state_survives_reloading() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("timex-timer.json");
+        let mut state = TimerState::load(&path).unwrap();
+        state.start(&project("P1"), None, at(9)).unwrap();
+        state.save(&path).unwrap();
+
+        let mut reloaded = TimerState::load(&path).unwrap();
+        assert_eq!(reloaded, state);
+        reloaded.stop(&[project("P1")], at(10)).unwrap();
+        reloaded.save(&path).unwrap();
+
+        assert_eq!(TimerState::load(&path).unwrap().entries.len(), 1);
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn test_concurrent_updates_keep_every_entry() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("timex-timer.json");
+        let projects = [project("P1")];
+
+        std::thread::scope(|scope| {
+            for _ in 0..8 {
+                scope.spawn(|| {
+                    TimerState::update(&path, |state| {
+                        state.start(&projects[0], None, at(9))?;
+                        state.stop(&projects, at(10))
+                    })
+                    .unwrap();
+                });
+            }
+        });
+
+        let state = TimerState::load(&path).unwrap();
+        assert_eq!(state.entries.len(), 8);
+        assert_eq!(state.entries[7].provenance.origin.as_deref(), Some("timer#8"));
+    }
+
+    #[test]
+    fn test_failed_update_saves_nothing() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("timex-timer.json");
+
+        let result = TimerState::update(&path, |state| {
+            state.start(&project("P1"), None, at(9))?;
+            state.stop(&[], at(10))
+        });
+
+        assert!(matches!(result, Err(TimerError::UnknownProject(_))));
+        assert_eq!(TimerState::load(&path).unwrap(), TimerState::default());
+    }
+}
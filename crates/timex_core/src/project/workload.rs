@@ -1,34 +1,55 @@
-use crate::git::diff::{get_commit_diff, CommitDiff, CommitTreeIterator, Walker};
+use crate::git::diff::{
+    get_edge_diffs_parallel, named_reference_tips, CommitDiff, CommitTreeIterator, MergePolicy, WalkOptions,
+    Walker,
+};
+use crate::git::identity::IdentityFilter;
 use crate::git::error::GitError;
-use async_openai::config::OpenAIConfig;
-use async_openai::types::ChatCompletionRequestUserMessage;
+use crate::git::GitCommit;
+use crate::pool::{map_bounded, ConcurrencyConfig};
+use crate::render::highlight::HighlightConfig;
+use crate::store::{Store, StoreError};
+use crate::summary::{LlmConfig, ProjectActivity, SummaryError, Summarizer};
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use gix::hash::ObjectId;
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use std::sync::Arc;
+use tokio::sync::Semaphore;
 use thiserror;
 use toml;
 
-use super::{ParseWorkloadError, Project, UserConfig};
-
-use async_openai::{
-    types::{ChatCompletionRequestMessage, CreateChatCompletionRequestArgs, Role},
-    Client as OpenAIClient,
-};
+use super::inference::{infer_task_sessions, InferenceConfig, WorkItem};
+use super::sync::{update_refs, ProjectSync, SyncState, SyncStateError};
+use super::timer::{RunningTimer, TimerCheck};
+use super::{ParseWorkloadError, Project, TimeEntry, UserConfig};
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct Workload {
     pub user: UserConfig,
     pub projects: Vec<Project>,
+    #[serde(default)]
+    pub inference: InferenceConfig,
+    #[serde(default)]
+    pub llm: LlmConfig,
+    #[serde(default)]
+    pub concurrency: ConcurrencyConfig,
+    #[serde(default)]
+    pub highlight: HighlightConfig,
 }
 
 #[derive(Debug, thiserror::Error)]
 pub enum WorkloadError {
     #[error("Git error: {0}")]
     Git(#[from] GitError),
-    #[error("OpenAI API error: {0}")]
-    OpenAI(#[from] async_openai::error::OpenAIError),
-    #[error("Failed to build chat completion request: {0}")]
-    RequestBuild(async_openai::error::OpenAIError),
+    #[error("Summary error: {0}")]
+    Summary(#[from] SummaryError),
+    #[error("Background git task failed: {0}")]
+    Task(#[from] tokio::task::JoinError),
+    #[error("Store error: {0}")]
+    Store(#[from] StoreError),
+    #[error("Sync state error: {0}")]
+    SyncState(#[from] SyncStateError),
 }
 
 impl Workload {
@@ -37,69 +58,495 @@ impl Workload {
         let config: Self = toml::from_str(&content)?;
         Ok(config)
     }
-    fn walk_commit_diffs(repo_path: &str) -> Result<Vec<CommitDiff>, GitError> {
-        let repo = gix::open(repo_path)?;
-        let mut walker = CommitTreeIterator::new(&repo)?;
-        let mut diffs: Vec<CommitDiff> = Vec::new();
 
-        walker.adjacent_pairs(|old_commit, new_commit| {
-            let diff = get_commit_diff(&repo, &old_commit.to_string(), &new_commit.to_string())?;
-            diffs.push(diff);
+    /// A workload for "Test User" on `projects` with every other setting left at its default.
+    #[cfg(test)]
+    pub(crate) fn for_test(projects: Vec<Project>) -> Workload {
+        Workload {
+            user: UserConfig {
+                name: "Test User".into(),
+                email: "test@example.com".into(),
+                emails: Vec::new(),
+                aliases: Vec::new(),
+                co_authors: false,
+            },
+            projects,
+            inference: InferenceConfig::default(),
+            llm: LlmConfig::default(),
+            concurrency: ConcurrencyConfig::default(),
+            highlight: HighlightConfig::default(),
+        }
+    }
+
+    /// Walk options selecting the configured user's own commits.
+    pub fn walk_options(&self) -> WalkOptions {
+        let filter = IdentityFilter::new(self.user.identity()).with_co_authors(self.user.co_authors);
+        WalkOptions::default().with_identity(filter)
+    }
+
+    /// Projects whose name or code matches `filter` case-insensitively, or every project without a filter.
+    pub fn select_projects(&self, filter: Option<&str>) -> Vec<&Project> {
+        self.projects
+            .iter()
+            .filter(|project| {
+                filter.is_none_or(|filter| {
+                    project.name.eq_ignore_ascii_case(filter) || project.code.eq_ignore_ascii_case(filter)
+                })
+            })
+            .collect()
+    }
+
+    /// The commits on `project` that pass `options`, newest first.
+    pub fn project_commits(&self, project: &Project, options: &WalkOptions) -> Result<Vec<GitCommit>, GitError> {
+        let repo = gix::open(&project.git_url)?;
+        let mut commits = Vec::new();
+        for id in CommitTreeIterator::with_options(&repo, options.clone())? {
+            commits.push(GitCommit::try_from(&repo.find_commit(id?)?)?);
+        }
+        Ok(commits)
+    }
+
+    /// The commits on `project` that pass `options`, together with the diffs they introduced.
+    pub fn project_activity(&self, project: &Project, options: &WalkOptions) -> Result<ProjectActivity, GitError> {
+        Self::activity_for(project, options, &self.concurrency)
+    }
+
+    /// The activity on each of `projects`, walking up to [`ConcurrencyConfig::max_repos`]
+    /// repositories at a time on the blocking thread pool, so the async executor stays responsive.
+    pub async fn collect_activity(
+        &self,
+        projects: &[&Project],
+        options: &WalkOptions,
+    ) -> Result<Vec<ProjectActivity>, WorkloadError> {
+        let semaphore = Arc::new(Semaphore::new(self.concurrency.repo_limit()));
+        let mut tasks = Vec::with_capacity(projects.len());
+        for project in projects {
+            let permit = semaphore.clone().acquire_owned().await.expect("the semaphore is never closed");
+            let (project, options, concurrency) = ((*project).clone(), options.clone(), self.concurrency.clone());
+            tasks.push(tokio::task::spawn_blocking(move || {
+                let _permit = permit;
+                Self::activity_for(&project, &options, &concurrency)
+            }));
+        }
+
+        let mut activities = Vec::with_capacity(tasks.len());
+        for task in tasks {
+            activities.push(task.await??);
+        }
+        Ok(activities)
+    }
+
+    /// Like [`Workload::collect_activity`], but walks only the history no earlier run recorded in
+    /// `state`, see [`Workload::incremental_activity`].
+    pub async fn collect_incremental_activity(
+        &self,
+        projects: &[&Project],
+        options: &WalkOptions,
+        state: &mut SyncState,
+        store: &Store,
+    ) -> Result<Vec<ProjectActivity>, WorkloadError> {
+        if options.revspec.is_some() {
+            return self.collect_activity(projects, options).await;
+        }
+        let semaphore = Arc::new(Semaphore::new(self.concurrency.repo_limit()));
+        let mut tasks = Vec::with_capacity(projects.len());
+        for project in projects {
+            let permit = semaphore.clone().acquire_owned().await.expect("the semaphore is never closed");
+            let (project, walk, concurrency) = ((*project).clone(), self.walk_options(), self.concurrency.clone());
+            let mut project_state = state.for_project(&project.code);
+            tasks.push(tokio::task::spawn_blocking(move || {
+                let _permit = permit;
+                let sync = Self::sync_with(&project, &walk, &concurrency, &mut project_state)?;
+                Ok::<_, GitError>((sync, project_state))
+            }));
+        }
+
+        let mut activities = Vec::with_capacity(tasks.len());
+        for task in tasks {
+            let (sync, project_state) = task.await??;
+            activities.push(Self::with_stored_history(sync, options, store)?);
+            state.absorb(project_state);
+        }
+        Ok(activities)
+    }
+
+    fn activity_for(
+        project: &Project,
+        options: &WalkOptions,
+        concurrency: &ConcurrencyConfig,
+    ) -> Result<ProjectActivity, GitError> {
+        let (commits, diffs) = Self::walk_commit_diffs(project, options, concurrency)?;
+        Ok(ProjectActivity {
+            project: project.clone(),
+            commits,
+            diffs,
+        })
+    }
+
+    /// Working sessions on `project` inferred from the commits that pass `options`.
+    pub fn project_time_entries(&self, project: &Project, options: &WalkOptions) -> Result<Vec<TimeEntry>, GitError> {
+        let items = Self::work_items(project, options)?;
+        Ok(infer_task_sessions(project, &items, &self.inference))
+    }
+
+    /// Walks the history sequentially, which only touches commit objects, then computes the diffs
+    /// on a worker pool as loading and diffing blobs is where the time goes.
+    fn walk_commit_diffs(
+        project: &Project,
+        options: &WalkOptions,
+        concurrency: &ConcurrencyConfig,
+    ) -> Result<(Vec<GitCommit>, Vec<CommitDiff>), GitError> {
+        let shared = gix::open(&project.git_url)?.into_sync();
+        let repo = shared.to_thread_local();
+        let mut walker = CommitTreeIterator::with_options(&repo, options.clone())?;
+        let mut commits: Vec<GitCommit> = Vec::new();
+        let mut edges = Vec::new();
+
+        walker.edges(|edge| {
+            commits.push(GitCommit::try_from(&repo.find_commit(edge.child)?)?);
+            edges.push(edge.clone());
             Ok(())
         })?;
 
-        Ok(diffs)
+        let diffs = get_edge_diffs_parallel(
+            &shared,
+            &edges,
+            MergePolicy::FirstParent,
+            &project.diff_options(),
+            concurrency.diff_thread_count(),
+        )?;
+        Ok((commits, diffs))
     }
 
-    pub fn generate_workload(&self) -> Result<HashMap<String, Vec<CommitDiff>>, GitError> {
-        let mut all_diffs = HashMap::new();
-        for project in &self.projects {
-            let diffs = Self::walk_commit_diffs(&project.git_url)?;
-            all_diffs.insert(project.name.clone(), diffs);
+    /// The user's commits and diffs for every project, walking several repositories at a time, each
+    /// with its share of the diff threads.
+    pub fn generate_activity(&self) -> Result<Vec<ProjectActivity>, GitError> {
+        let options = self.walk_options();
+        map_bounded(&self.projects, self.concurrency.repo_limit(), || (), |_, project| {
+            self.project_activity(project, &options)
+        })
+        .into_iter()
+        .collect()
+    }
+
+    /// The user's activity on `project` that no earlier sync recorded in `state` has processed.
+    ///
+    /// Only the ancestry between the refs' previous and current tips is walked, and `state` is
+    /// updated to the current tips.
+    pub fn sync_project(&self, project: &Project, state: &mut SyncState) -> Result<ProjectSync, GitError> {
+        Self::sync_with(project, &self.walk_options(), &self.concurrency, state)
+    }
+
+    fn sync_with(
+        project: &Project,
+        options: &WalkOptions,
+        concurrency: &ConcurrencyConfig,
+        state: &mut SyncState,
+    ) -> Result<ProjectSync, GitError> {
+        let repo = gix::open(&project.git_url)?;
+        let tips = named_reference_tips(&repo)?;
+        let options = options.clone().with_hidden(state.known_tips(&repo, &project.code));
+        let (commits, diffs) = Self::walk_commit_diffs(project, &options, concurrency)?;
+        let updates = update_refs(&repo, state, &project.code, &tips)?;
+
+        Ok(ProjectSync {
+            activity: ProjectActivity {
+                project: project.clone(),
+                commits,
+                diffs,
+            },
+            updates,
+        })
+    }
+
+    /// Syncs every project, see [`Workload::sync_project`].
+    pub fn sync(&self, state: &mut SyncState) -> Result<Vec<ProjectSync>, GitError> {
+        self.projects
+            .iter()
+            .map(|project| self.sync_project(project, state))
+            .collect()
+    }
+
+    /// The diffs of the user's commits on every project, keyed by project name.
+    ///
+    /// The last processed tip of every ref is kept in the state file at `state_path`, and the
+    /// commits behind them in `store`, so that later runs only walk new history.
+    pub fn generate_workload(
+        &self,
+        state_path: impl AsRef<Path>,
+        store: &Store,
+    ) -> Result<HashMap<String, Vec<CommitDiff>>, WorkloadError> {
+        let mut state = SyncState::load(&state_path)?;
+        let options = self.walk_options();
+        let results = map_bounded(&self.projects, self.concurrency.repo_limit(), || (), |_, project| {
+            let mut project_state = state.for_project(&project.code);
+            let activity = self.incremental_activity(project, &options, &mut project_state, store)?;
+            Ok::<_, WorkloadError>((activity, project_state))
+        });
+
+        let mut workload = HashMap::new();
+        for result in results {
+            let (activity, project_state) = result?;
+            state.absorb(project_state);
+            workload.insert(activity.project.name, activity.diffs);
         }
-        Ok(all_diffs)
+        state.save(&state_path)?;
+        Ok(workload)
     }
 
-    pub async fn generate_report(
+    /// The user's activity on `project` within `options`, walking only the history that no
+    /// earlier run recorded in `state` and taking older commits from `store`.
+    ///
+    /// `store` must hold what `state` says was processed, so the new activity is saved to it and
+    /// `state` updated together. Older commits carry line counts but no hunks. A revspec in
+    /// `options` cannot be answered from the store, so it walks the full history instead.
+    pub fn incremental_activity(
         &self,
-        api_key: &str,
-    ) -> Result<HashMap<String, String>, WorkloadError> {
-        let config = OpenAIConfig::default().with_api_key(api_key);
-        let client = OpenAIClient::with_config(config);
-        let workload = self.generate_workload()?;
-        let mut reports = HashMap::new();
+        project: &Project,
+        options: &WalkOptions,
+        state: &mut SyncState,
+        store: &Store,
+    ) -> Result<ProjectActivity, WorkloadError> {
+        if options.revspec.is_some() {
+            return Ok(self.project_activity(project, options)?);
+        }
+        let sync = self.sync_project(project, state)?;
+        Self::with_stored_history(sync, options, store)
+    }
+
+    /// Saves the newly synced activity to `store` and adds the stored commits before it, keeping
+    /// those within `options`, newest first.
+    fn with_stored_history(
+        sync: ProjectSync,
+        options: &WalkOptions,
+        store: &Store,
+    ) -> Result<ProjectActivity, WorkloadError> {
+        store.save_activity(&sync.activity)?;
+        let ProjectActivity { project, commits, diffs } = sync.activity;
+
+        let new: HashSet<String> = commits.iter().map(|commit| commit.hash.clone()).collect();
+        let mut new_diffs: HashMap<String, Vec<CommitDiff>> = HashMap::new();
+        for diff in diffs {
+            new_diffs.entry(diff.new_commit.clone()).or_default().push(diff);
+        }
+        let mut commits: Vec<GitCommit> = commits
+            .into_iter()
+            .chain(store.commits(&project.code)?.into_iter().filter(|commit| !new.contains(&commit.hash)))
+            .filter(|commit| options.includes(commit))
+            .collect();
+        commits.sort_by_key(|commit| std::cmp::Reverse(commit.committer_date));
 
-        for (project_name, diffs) in workload {
-            let mut diff_descriptions = String::new();
-            for diff in diffs {
-                diff_descriptions.push_str(&format!("{}\n", diff));
+        let mut diffs = Vec::with_capacity(commits.len());
+        for commit in &commits {
+            match new_diffs.remove(&commit.hash) {
+                Some(commit_diffs) => diffs.extend(commit_diffs),
+                None if new.contains(&commit.hash) => {}
+                None => diffs.extend(store.commit_diffs(&project.code, &commit.hash)?),
             }
+        }
+        Ok(ProjectActivity { project, commits, diffs })
+    }
+
+    /// Working sessions on `project` inferred like [`Workload::project_time_entries`], from the
+    /// commits of [`Workload::incremental_activity`].
+    pub fn incremental_time_entries(
+        &self,
+        project: &Project,
+        options: &WalkOptions,
+        state: &mut SyncState,
+        store: &Store,
+    ) -> Result<Vec<TimeEntry>, WorkloadError> {
+        let activity = self.incremental_activity(project, options, state, store)?;
+        let items = Self::commit_work_items(project, &activity.commits, options)?;
+        Ok(infer_task_sessions(project, &items, &self.inference))
+    }
+
+    /// When each commit passing `options` was authored, and the ticket it names in its message or
+    /// was committed for on a ticket branch.
+    fn work_items(project: &Project, options: &WalkOptions) -> Result<Vec<WorkItem>, GitError> {
+        let repo = gix::open(&project.git_url)?;
+        let mut commits = Vec::new();
+        for id in CommitTreeIterator::with_options(&repo, options.clone())? {
+            commits.push(GitCommit::try_from(&repo.find_commit(id?)?)?);
+        }
+        Self::commit_work_items(project, &commits, options)
+    }
 
-            let messages = vec![
-                ChatCompletionRequestMessage::User(ChatCompletionRequestUserMessage {
-                    content: async_openai::types::ChatCompletionRequestUserMessageContent::Text(format!(
-                        "Please analyze these git commits and write a brief summary of the work done:\n{}",
-                        diff_descriptions
-                    )),
-                    name: None,
+    /// When each of `commits` was authored, and its ticket; branches are searched for tickets
+    /// within the window of `options`.
+    fn commit_work_items(
+        project: &Project,
+        commits: &[GitCommit],
+        options: &WalkOptions,
+    ) -> Result<Vec<WorkItem>, GitError> {
+        let repo = gix::open(&project.git_url)?;
+        let branch_tickets = project.tickets.branch_tickets(&repo, options.since, options.until)?;
+        commits
+            .iter()
+            .map(|commit| {
+                let id = ObjectId::from_hex(commit.hash.as_bytes())?;
+                Ok(WorkItem {
+                    time: commit.author_date.to_utc(),
+                    task: project.tickets.for_commit(commit).or_else(|| branch_tickets.get(&id).cloned()),
+                    utc_offset: Some(commit.author_date.offset().local_minus_utc()),
                 })
-            ];
+            })
+            .collect()
+    }
 
-            let request = CreateChatCompletionRequestArgs::default()
-                .model("gpt-4-turbo-preview")
-                .messages(messages)
-                .build()?;
+    /// Compares `timer`, running until `end`, with the user's commits on every project authored
+    /// in that time. Tickets are only looked up on the timed project.
+    pub fn check_timer(&self, timer: &RunningTimer, end: DateTime<Utc>) -> Result<TimerCheck, GitError> {
+        let options = self.walk_options().with_since(timer.start_time).with_until(end);
+        let running = |time: &DateTime<Utc>| (timer.start_time..=end).contains(time);
+        let mut check = TimerCheck::default();
+        for project in &self.projects {
+            if project.code != timer.project {
+                let commits = self.project_commits(project, &options)?;
+                let count = commits.iter().filter(|commit| running(&commit.author_date.to_utc())).count();
+                if count > 0 {
+                    check.elsewhere.insert(project.code.clone(), count);
+                }
+                continue;
+            }
+            for item in Self::work_items(project, &options)?.iter().filter(|item| running(&item.time)) {
+                check.on_project += 1;
+                if let (Some(task), Some(timed)) = (&item.task, &timer.task) {
+                    if task != timed {
+                        *check.other_tasks.entry(task.clone()).or_default() += 1;
+                    }
+                }
+            }
+        }
+        Ok(check)
+    }
 
-            let response = client.chat().create(request).await?;
-            let summary = response.choices[0]
-                .message
-                .content
-                .clone()
-                .unwrap_or_default();
+    /// Infers working sessions for every project from the user's commit history.
+    pub fn infer_time_entries(&self) -> Result<Vec<TimeEntry>, GitError> {
+        let options = self.walk_options();
+        let mut entries = Vec::new();
+        for project in &self.projects {
+            entries.extend(self.project_time_entries(project, &options)?);
+        }
+        Ok(entries)
+    }
 
-            reports.insert(project_name, summary);
+    /// Summarises the user's work on every project with `summarizer`, keyed by project name.
+    pub async fn generate_report(
+        &self,
+        summarizer: &dyn Summarizer,
+    ) -> Result<HashMap<String, String>, WorkloadError> {
+        let mut reports = HashMap::new();
+        let projects: Vec<&Project> = self.projects.iter().collect();
+
+        for activity in self.collect_activity(&projects, &self.walk_options()).await? {
+            let summary = summarizer.summarize(&activity).await?;
+            reports.insert(activity.project.name, summary);
         }
 
         Ok(reports)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git::test_repo::TestRepo;
+    use crate::project::tickets::TicketPatterns;
+    use crate::summary::OfflineSummarizer;
+    use chrono::NaiveDate;
+
+    #[tokio::test]
+    async fn test_generate_report_offline() {
+        let repo = TestRepo::new();
+        repo.commit("src/lib.rs", "fn a() {}\n", "Add a");
+        repo.commit("src/lib.rs", "fn a() {}\nfn b() {}\n", "Add b");
+        repo.commit_with_env(
+            "other.rs",
+            "x\n",
+            "Someone else's work",
+            &[("GIT_AUTHOR_NAME", "Other"), ("GIT_AUTHOR_EMAIL", "other@example.com"),
+              ("GIT_COMMITTER_NAME", "Other"), ("GIT_COMMITTER_EMAIL", "other@example.com")],
+        );
+
+        let workload = Workload::for_test(vec![Project {
+            name: "Test".into(),
+            description: "Test project".into(),
+            ..Project::for_test("T1", repo.path().to_str().unwrap())
+        }]);
+
+        let report = workload.generate_report(&OfflineSummarizer::default()).await.unwrap();
+        let summary = &report["Test"];
+
+        assert!(summary.starts_with("Test: 2 commits, 1 files changed (+2, -0)"), "{summary}");
+        assert!(summary.contains("- Add b ("));
+        assert!(!summary.contains("Someone else's work"));
+    }
+
+    #[test]
+    fn test_timer_check_counts_commits_while_running() {
+        let repos = [TestRepo::new(), TestRepo::new()];
+        let commit_at = |repo: &TestRepo, file: &str, message: &str, time: &str| {
+            repo.commit_with_env(file, message, message, &[("GIT_AUTHOR_DATE", time), ("GIT_COMMITTER_DATE", time)]);
+        };
+        commit_at(&repos[0], "a.txt", "ABC-1: start", "2024-01-01T10:00:00Z");
+        commit_at(&repos[0], "b.txt", "ABC-2: aside", "2024-01-01T11:00:00Z");
+        commit_at(&repos[0], "c.txt", "ABC-1: later", "2024-01-01T13:00:00Z");
+        commit_at(&repos[1], "a.txt", "elsewhere", "2024-01-01T10:30:00Z");
+
+        let project = |code: &str, repo: &TestRepo| Project {
+            tickets: TicketPatterns::new(&[r"[A-Z]+-\d+"]).unwrap(),
+            ..Project::for_test(code, repo.path().to_str().unwrap())
+        };
+        let workload = Workload::for_test(vec![project("T1", &repos[0]), project("T2", &repos[1])]);
+        let at = |hour| DateTime::parse_from_rfc3339(&format!("2024-01-01T{hour:02}:00:00Z")).unwrap().to_utc();
+        let timer = RunningTimer {
+            project: "T1".into(),
+            task: Some("ABC-1".into()),
+            start_time: at(9),
+        };
+
+        let check = workload.check_timer(&timer, at(12)).unwrap();
+
+        assert_eq!(check.on_project, 2);
+        assert_eq!(check.elsewhere, [("T2".to_string(), 1)].into());
+        assert_eq!(check.other_tasks, [("ABC-2".to_string(), 1)].into());
+        assert!(!check.agrees());
+    }
+
+    #[test]
+    fn test_entries_fall_on_the_authors_day() {
+        let repo = TestRepo::new();
+        let time = "2024-01-02T00:45:00+09:00";
+        repo.commit_with_env("a.txt", "1\n", "late", &[("GIT_AUTHOR_DATE", time), ("GIT_COMMITTER_DATE", time)]);
+        let workload = Workload::for_test(vec![Project::for_test("T1", repo.path().to_str().unwrap())]);
+
+        let entries = workload.project_time_entries(&workload.projects[0], &workload.walk_options()).unwrap();
+
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].utc_offset, Some(9 * 3600));
+        assert_eq!(entries[0].start_time.date_naive(), NaiveDate::from_ymd_opt(2024, 1, 1).unwrap());
+        assert_eq!(entries[0].work_day(), NaiveDate::from_ymd_opt(2024, 1, 2).unwrap());
+        assert_eq!(entries[0].local_end().format("%H:%M").to_string(), "00:45");
+    }
+
+    #[test]
+    fn test_co_authored_commits_count_only_when_enabled() {
+        let repo = TestRepo::new();
+        repo.commit_with_env(
+            "a.txt",
+            "1\n",
+            "Pair on the parser\n\nCo-authored-by: Test User <test@example.com>",
+            &[("GIT_AUTHOR_NAME", "Other"), ("GIT_AUTHOR_EMAIL", "other@example.com"),
+              ("GIT_COMMITTER_NAME", "Other"), ("GIT_COMMITTER_EMAIL", "other@example.com")],
+        );
+        let mut workload = Workload::for_test(vec![Project::for_test("T1", repo.path().to_str().unwrap())]);
+        let project = workload.projects[0].clone();
+
+        assert!(workload.project_commits(&project, &workload.walk_options()).unwrap().is_empty());
+        workload.user.co_authors = true;
+        let commits = workload.project_commits(&project, &workload.walk_options()).unwrap();
+        assert_eq!(commits.len(), 1);
+        assert_eq!(commits[0].title, "Pair on the parser");
+    }
+}
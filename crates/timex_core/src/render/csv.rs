@@ -0,0 +1,25 @@
+use super::Tabular;
+
+/// Renders `items` as RFC 4180 CSV with a header row.
+pub fn render<T: Tabular>(items: &[T]) -> String {
+    let mut out = String::new();
+    push_record(&mut out, T::HEADERS.iter().copied());
+    for row in items.iter().flat_map(Tabular::rows) {
+        push_record(&mut out, row.iter().map(String::as_str));
+    }
+    out
+}
+
+fn push_record<'a>(out: &mut String, fields: impl Iterator<Item = &'a str>) {
+    let fields: Vec<String> = fields.map(escape).collect();
+    out.push_str(&fields.join(","));
+    out.push('\n');
+}
+
+fn escape(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
@@ -0,0 +1,303 @@
+use super::highlight::{FileHighlighter, Highlighter};
+use super::html::{document, escape};
+use crate::git::diff::{CommitDiff, DiffLine, DiffLineType, FileChange};
+use crate::summary::ProjectSummary;
+use serde::{Deserialize, Serialize};
+use syntect::highlighting::{Color, Style};
+use syntect::html::{styled_line_to_highlighted_html, IncludeBackground};
+
+/// How the two sides of a diff are laid out in HTML.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DiffLayout {
+    /// One column with removed lines above the added lines that replace them, like `git diff`.
+    #[default]
+    Unified,
+    /// The old file on the left and the new file on the right.
+    SideBySide,
+}
+
+const STYLE: &str = "body{font-family:system-ui,sans-serif;margin:2rem;color:#222}\
+h2{font-size:1.1rem;margin-top:2rem}\
+h3{font-family:ui-monospace,monospace;font-size:.95rem;margin:1.2rem 0 .4rem}\
+.meta{color:#666;font-weight:normal}\
+table.diff{border-collapse:collapse;width:100%;font-family:ui-monospace,monospace;font-size:.85rem}\
+table.diff td{padding:0 .5rem;white-space:pre-wrap;vertical-align:top}\
+td.num{color:#888;text-align:right;user-select:none;width:1%}\
+td.sign{user-select:none;width:1%}\
+tr.hunk td{color:#888;padding:.3rem .5rem}\
+td.add{box-shadow:inset 4px 0 #2da44e}\
+td.del{box-shadow:inset 4px 0 #cf222e}";
+
+/// Renders `diffs` as a standalone HTML document, syntax highlighted with `highlighter` if given.
+///
+/// All styles are inlined, so the file can be attached or pasted into a wiki as it is.
+pub fn render(title: &str, diffs: &[CommitDiff], highlighter: Option<&Highlighter>, layout: DiffLayout) -> String {
+    let body = sections(diffs, highlighter, layout);
+    document(title, &style(highlighter), &body)
+}
+
+/// Renders a report as one standalone HTML document: each project's summary followed by its
+/// highlighted diffs, collapsed by default.
+pub fn render_report(
+    title: &str,
+    projects: &[(&ProjectSummary, &[CommitDiff])],
+    highlighter: Option<&Highlighter>,
+    layout: DiffLayout,
+) -> String {
+    let mut body = String::new();
+    for (summary, diffs) in projects {
+        body.push_str(&format!(
+            "<h2>{} <span class=\"meta\">{}</span></h2>\n<p style=\"white-space:pre-wrap\">{}</p>\n",
+            escape(&summary.project),
+            escape(&summary.code),
+            escape(&summary.summary)
+        ));
+        if !diffs.is_empty() {
+            body.push_str(&format!(
+                "<details>\n<summary>{} diffs</summary>\n{}</details>\n",
+                diffs.len(),
+                sections(diffs, highlighter, layout)
+            ));
+        }
+    }
+    document(title, &style(highlighter), &body)
+}
+
+/// The styles [`sections`] rely on, for embedding them into a larger document.
+pub fn style(highlighter: Option<&Highlighter>) -> String {
+    let background = highlighter
+        .and_then(|highlighter| highlighter.theme().settings.background)
+        .map_or("#f8f8f8".to_string(), css_color);
+    let foreground = highlighter
+        .and_then(|highlighter| highlighter.theme().settings.foreground)
+        .map_or("#222".to_string(), css_color);
+    format!("{STYLE}table.diff{{background:{background};color:{foreground}}}")
+}
+
+/// One `<h2>` section per commit diff with a table per changed file.
+pub fn sections(diffs: &[CommitDiff], highlighter: Option<&Highlighter>, layout: DiffLayout) -> String {
+    let mut body = String::new();
+    for diff in diffs {
+        body.push_str(&format!(
+            "<h2>{} <span class=\"meta\">from {}</span></h2>\n",
+            escape(&diff.new_commit),
+            escape(&diff.old_commit)
+        ));
+        for change in &diff.changes {
+            body.push_str(&file_table(change, highlighter, layout));
+        }
+    }
+    body
+}
+
+fn file_table(change: &FileChange, highlighter: Option<&Highlighter>, layout: DiffLayout) -> String {
+    let mut html = format!("<h3>{}", escape(&change.path));
+    if let Some(source) = &change.source_path {
+        html.push_str(&format!(" <span class=\"meta\">from {}</span>", escape(source)));
+    }
+    if let Some(label) = change.opaque_label() {
+        html.push_str(&format!(" <span class=\"meta\">{}</span></h3>\n", escape(&label)));
+        return html;
+    }
+    html.push_str(&format!(
+        " <span class=\"meta\">+{} -{}</span></h3>\n<table class=\"diff\">\n",
+        change.lines_added, change.lines_removed
+    ));
+
+    let mut file = highlighter.map(|highlighter| highlighter.file(&change.path));
+    let columns = match layout {
+        DiffLayout::Unified => 4,
+        DiffLayout::SideBySide => 6,
+    };
+    for hunk in &change.hunks {
+        html.push_str(&format!(
+            "<tr class=\"hunk\"><td colspan=\"{}\">{}</td></tr>\n",
+            columns,
+            escape(&hunk.header())
+        ));
+        let cells = Cells::new(hunk.old_range.start, hunk.new_range.start, &hunk.lines, file.as_mut());
+        match layout {
+            DiffLayout::Unified => cells.unified(&mut html),
+            DiffLayout::SideBySide => cells.side_by_side(&mut html),
+        }
+    }
+
+    html.push_str("</table>\n");
+    html
+}
+
+/// The lines of one hunk with their line numbers and rendered content.
+struct Cells<'a> {
+    lines: Vec<(&'a DiffLine, Option<u32>, Option<u32>, String)>,
+}
+
+impl<'a> Cells<'a> {
+    fn new(old_start: u32, new_start: u32, lines: &'a [DiffLine], mut file: Option<&mut FileHighlighter<'_>>) -> Self {
+        let (mut old, mut new) = (old_start, new_start);
+        let lines = lines
+            .iter()
+            .map(|line| {
+                let content = match file.as_deref_mut() {
+                    Some(file) => highlighted(&file.highlight(line), line),
+                    None => escape(&line.content),
+                };
+                let (old_number, new_number) = match line.line_type {
+                    DiffLineType::Context => (Some(old), Some(new)),
+                    DiffLineType::Removed => (Some(old), None),
+                    DiffLineType::Added => (None, Some(new)),
+                };
+                old += old_number.is_some() as u32;
+                new += new_number.is_some() as u32;
+                (line, old_number, new_number, content)
+            })
+            .collect();
+        Cells { lines }
+    }
+
+    fn unified(&self, html: &mut String) {
+        for (line, old, new, content) in &self.lines {
+            let (class, sign) = class_and_sign(line.line_type);
+            html.push_str(&format!(
+                "<tr><td class=\"num\">{}</td><td class=\"num\">{}</td><td class=\"sign {class}\">{sign}</td><td>{content}</td></tr>\n",
+                number(*old),
+                number(*new),
+            ));
+        }
+    }
+
+    /// Pairs each run of removed lines with the run of added lines that follows it.
+    fn side_by_side(&self, html: &mut String) {
+        let mut i = 0;
+        while i < self.lines.len() {
+            if self.lines[i].0.line_type == DiffLineType::Context {
+                let (_, old, new, content) = &self.lines[i];
+                let context = |number| Some((number, content, DiffLineType::Context));
+                html.push_str(&row(context(*old), context(*new)));
+                i += 1;
+                continue;
+            }
+
+            let run_of = |start: usize, line_type: DiffLineType| {
+                self.lines[start..].iter().take_while(|(line, ..)| line.line_type == line_type).count()
+            };
+            let removed = run_of(i, DiffLineType::Removed);
+            let added = run_of(i + removed, DiffLineType::Added);
+            for k in 0..removed.max(added) {
+                let left = (k < removed).then(|| {
+                    let (_, old, _, content) = &self.lines[i + k];
+                    (*old, content, DiffLineType::Removed)
+                });
+                let right = (k < added).then(|| {
+                    let (_, _, new, content) = &self.lines[i + removed + k];
+                    (*new, content, DiffLineType::Added)
+                });
+                html.push_str(&row(left, right));
+            }
+            i += removed + added;
+        }
+    }
+}
+
+type Side<'a> = Option<(Option<u32>, &'a String, DiffLineType)>;
+
+fn row(left: Side<'_>, right: Side<'_>) -> String {
+    let mut html = String::from("<tr>");
+    for side in [left, right] {
+        match side {
+            Some((line_number, content, line_type)) => {
+                let (class, sign) = class_and_sign(line_type);
+                html.push_str(&format!(
+                    "<td class=\"num\">{}</td><td class=\"sign {class}\">{sign}</td><td>{content}</td>",
+                    number(line_number)
+                ));
+            }
+            None => html.push_str("<td class=\"num\"></td><td class=\"sign\"></td><td></td>"),
+        }
+    }
+    html.push_str("</tr>\n");
+    html
+}
+
+fn class_and_sign(line_type: DiffLineType) -> (&'static str, &'static str) {
+    match line_type {
+        DiffLineType::Added => ("add", "+"),
+        DiffLineType::Removed => ("del", "-"),
+        DiffLineType::Context => ("", " "),
+    }
+}
+
+fn number(number: Option<u32>) -> String {
+    number.map(|n| n.to_string()).unwrap_or_default()
+}
+
+/// The highlighted spans of `line` as HTML, or its plain escaped content should highlighting fail,
+/// so that no line ever goes missing from the page.
+fn highlighted(spans: &[(Style, String)], line: &DiffLine) -> String {
+    let spans: Vec<(Style, &str)> = spans.iter().map(|(style, text)| (*style, text.as_str())).collect();
+    styled_line_to_highlighted_html(&spans, IncludeBackground::No).unwrap_or_else(|_| escape(&line.content))
+}
+
+fn css_color(color: Color) -> String {
+    format!("#{:02x}{:02x}{:02x}", color.r, color.g, color.b)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git::diff::ChangeType;
+    use crate::git::hunk::build_hunks;
+    use crate::render::highlight::DEFAULT_THEME;
+
+    fn diff() -> CommitDiff {
+        let old = ["fn a() {}", "fn b() {}", "fn c() {}"];
+        let new = ["fn a() {}", "fn b2() {}", "fn b3() {}", "fn c() {}"];
+        CommitDiff {
+            old_commit: "old".into(),
+            new_commit: "new".into(),
+            changes: vec![FileChange {
+                path: "src/<lib>.rs".into(),
+                change_type: ChangeType::Modified,
+                lines_added: 2,
+                lines_removed: 1,
+                source_path: None,
+                similarity: None,
+                hunks: build_hunks(&old, &new, &[(1..2, 1..3)], 1),
+                attributes: Default::default(),
+                content: Default::default(),
+            }],
+        }
+    }
+
+    #[test]
+    fn test_unified_layout_numbers_lines() {
+        let html = render("Diff", &[diff()], None, DiffLayout::Unified);
+
+        assert!(html.contains("<h3>src/&lt;lib&gt;.rs"));
+        assert!(html.contains("<td class=\"num\">2</td><td class=\"num\"></td><td class=\"sign del\">-</td><td>fn b() {}</td>"));
+        assert!(html.contains("<td class=\"num\"></td><td class=\"num\">3</td><td class=\"sign add\">+</td><td>fn b3() {}</td>"));
+    }
+
+    #[test]
+    fn test_side_by_side_pairs_replacements() {
+        let html = render("Diff", &[diff()], None, DiffLayout::SideBySide);
+
+        assert!(html.contains(
+            "<td class=\"num\">2</td><td class=\"sign del\">-</td><td>fn b() {}</td>\
+             <td class=\"num\">2</td><td class=\"sign add\">+</td><td>fn b2() {}</td>"
+        ));
+        assert!(html.contains(
+            "<td class=\"num\"></td><td class=\"sign\"></td><td></td>\
+             <td class=\"num\">3</td><td class=\"sign add\">+</td><td>fn b3() {}</td>"
+        ));
+    }
+
+    #[test]
+    fn test_highlighting_is_inline() {
+        let highlighter = Highlighter::new(DEFAULT_THEME).unwrap();
+        let html = render("Diff", &[diff()], Some(&highlighter), DiffLayout::Unified);
+
+        assert!(html.contains("<span style=\"color:#"));
+        assert!(!html.contains("<link"));
+    }
+}
@@ -0,0 +1,226 @@
+use crate::git::diff::{CommitDiff, DiffLine, DiffLineType, FileChange};
+use colored::*;
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::path::Path;
+use syntect::easy::HighlightLines;
+use syntect::highlighting::{Style, Theme, ThemeSet};
+use syntect::parsing::{SyntaxReference, SyntaxSet};
+use syntect::util::as_24_bit_terminal_escaped;
+
+lazy_static! {
+    static ref SYNTAXES: SyntaxSet = SyntaxSet::load_defaults_newlines();
+    static ref THEMES: ThemeSet = ThemeSet::load_defaults();
+}
+
+pub const DEFAULT_THEME: &str = "base16-ocean.dark";
+
+#[derive(Debug, thiserror::Error)]
+pub enum HighlightError {
+    #[error("Unknown theme '{name}', expected a .tmTheme file or one of: {available}")]
+    UnknownTheme { name: String, available: String },
+    #[error("Failed to load theme: {0}")]
+    LoadTheme(#[from] syntect::LoadingError),
+}
+
+/// How diffs are syntax highlighted on the terminal.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct HighlightConfig {
+    /// A built-in syntect theme name or the path to a `.tmTheme` file.
+    pub theme: String,
+    /// Set to `false` to keep the plain red and green diff colours.
+    pub enabled: bool,
+}
+
+impl Default for HighlightConfig {
+    fn default() -> Self {
+        HighlightConfig {
+            theme: DEFAULT_THEME.into(),
+            enabled: true,
+        }
+    }
+}
+
+/// Syntax highlights diffs with one theme.
+pub struct Highlighter {
+    theme: Theme,
+}
+
+impl Highlighter {
+    pub fn new(theme: &str) -> Result<Self, HighlightError> {
+        if let Some(theme) = THEMES.themes.get(theme) {
+            return Ok(Highlighter { theme: theme.clone() });
+        }
+        if Path::new(theme).is_file() {
+            return Ok(Highlighter {
+                theme: ThemeSet::get_theme(theme)?,
+            });
+        }
+        Err(HighlightError::UnknownTheme {
+            name: theme.into(),
+            available: THEMES.themes.keys().cloned().collect::<Vec<_>>().join(", "),
+        })
+    }
+
+    /// The highlighter for `config`, or `None` if highlighting is disabled.
+    pub fn from_config(config: &HighlightConfig) -> Result<Option<Self>, HighlightError> {
+        config.enabled.then(|| Self::new(&config.theme)).transpose()
+    }
+
+    pub fn theme(&self) -> &Theme {
+        &self.theme
+    }
+
+    /// A fresh highlighter for the hunks of the file at `path`.
+    pub fn file(&self, path: &str) -> FileHighlighter<'_> {
+        let syntax = syntax_for(path);
+        FileHighlighter {
+            old: HighlightLines::new(syntax, &self.theme),
+            new: HighlightLines::new(syntax, &self.theme),
+        }
+    }
+
+    /// Displays `diff` with 24-bit terminal colours.
+    pub fn terminal<'a>(&'a self, diff: &'a CommitDiff) -> TerminalDiff<'a> {
+        TerminalDiff { diff, highlighter: self }
+    }
+}
+
+fn syntax_for(path: &str) -> &'static SyntaxReference {
+    let path = Path::new(path);
+    let by_name = path.file_name().and_then(|name| name.to_str());
+    let by_extension = path.extension().and_then(|ext| ext.to_str());
+    [by_extension, by_name]
+        .into_iter()
+        .flatten()
+        .find_map(|token| SYNTAXES.find_syntax_by_extension(token))
+        .unwrap_or_else(|| SYNTAXES.find_syntax_plain_text())
+}
+
+/// Highlights the lines of one file in order, carrying the parser state from line to line so that
+/// multi-line strings and comments come out right.
+///
+/// The old and new sides of the file are parsed separately: removed lines only advance the old
+/// side, added lines the new one, and context lines both.
+pub struct FileHighlighter<'a> {
+    old: HighlightLines<'a>,
+    new: HighlightLines<'a>,
+}
+
+impl FileHighlighter<'_> {
+    /// The styled spans of `line`. Must be called for every line of the file's hunks, in order.
+    pub fn highlight(&mut self, line: &DiffLine) -> Vec<(Style, String)> {
+        // The syntax set expects lines to end in a newline.
+        let text = format!("{}\n", line.content);
+        let spans = match line.line_type {
+            DiffLineType::Removed => spans(&mut self.old, &text),
+            DiffLineType::Added => spans(&mut self.new, &text),
+            DiffLineType::Context => {
+                spans(&mut self.old, &text);
+                spans(&mut self.new, &text)
+            }
+        };
+        spans
+            .into_iter()
+            .map(|(style, text)| (style, text.trim_end_matches('\n').to_string()))
+            .filter(|(_, text)| !text.is_empty())
+            .collect()
+    }
+}
+
+fn spans(lines: &mut HighlightLines<'_>, text: &str) -> Vec<(Style, String)> {
+    match lines.highlight_line(text, &SYNTAXES) {
+        Ok(ranges) => ranges.into_iter().map(|(style, text)| (style, text.to_string())).collect(),
+        Err(_) => vec![(Style::default(), text.to_string())],
+    }
+}
+
+/// A [`CommitDiff`] displayed with syntax highlighting for a 24-bit colour terminal.
+pub struct TerminalDiff<'a> {
+    diff: &'a CommitDiff,
+    highlighter: &'a Highlighter,
+}
+
+impl TerminalDiff<'_> {
+    fn write_change(&self, f: &mut fmt::Formatter<'_>, change: &FileChange) -> fmt::Result {
+        change.write_header(f)?;
+        let mut file = self.highlighter.file(&change.path);
+        for hunk in &change.hunks {
+            writeln!(f, "{}", hunk.header().cyan())?;
+            for line in &hunk.lines {
+                let prefix = match line.line_type {
+                    DiffLineType::Added => "+".green(),
+                    DiffLineType::Removed => "-".red(),
+                    DiffLineType::Context => " ".normal(),
+                };
+                let spans = file.highlight(line);
+                let spans: Vec<(Style, &str)> = spans.iter().map(|(style, text)| (*style, text.as_str())).collect();
+                writeln!(f, "{} {}\x1b[0m", prefix, as_24_bit_terminal_escaped(&spans, false))?;
+            }
+        }
+        writeln!(f)
+    }
+}
+
+impl fmt::Display for TerminalDiff<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "Commit diff {} -> {}",
+            self.diff.old_commit.bright_yellow(),
+            self.diff.new_commit.bright_yellow()
+        )?;
+        for change in &self.diff.changes {
+            self.write_change(f, change)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn line(line_type: DiffLineType, content: &str) -> DiffLine {
+        DiffLine {
+            line_type,
+            content: content.into(),
+        }
+    }
+
+    #[test]
+    fn test_state_carries_across_lines() {
+        let highlighter = Highlighter::new(DEFAULT_THEME).unwrap();
+        let comment_end = line(DiffLineType::Added, "still a comment */");
+
+        let mut stateful = highlighter.file("lib.rs");
+        stateful.highlight(&line(DiffLineType::Context, "/* a comment"));
+        let in_comment = stateful.highlight(&comment_end);
+
+        let alone = highlighter.file("lib.rs").highlight(&comment_end);
+
+        let comment_style = in_comment[0].0;
+        assert!(in_comment.iter().all(|(style, _)| *style == comment_style), "{in_comment:?}");
+        assert_ne!(in_comment, alone);
+    }
+
+    #[test]
+    fn test_removed_lines_do_not_affect_new_side() {
+        let highlighter = Highlighter::new(DEFAULT_THEME).unwrap();
+        let code = line(DiffLineType::Added, "let x = 1;");
+
+        let mut file = highlighter.file("lib.rs");
+        file.highlight(&line(DiffLineType::Removed, "/* a comment"));
+        let after_removal = file.highlight(&code);
+
+        assert_eq!(after_removal, highlighter.file("lib.rs").highlight(&code));
+    }
+
+    #[test]
+    fn test_unknown_theme_is_rejected() {
+        let error = Highlighter::new("no-such-theme").err().unwrap();
+        assert!(error.to_string().contains(DEFAULT_THEME));
+    }
+}
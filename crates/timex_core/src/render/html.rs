@@ -0,0 +1,47 @@
+use super::Tabular;
+
+const STYLE: &str = "body{font-family:system-ui,sans-serif;margin:2rem;color:#222}\
+table{border-collapse:collapse;margin-bottom:2rem}\
+th,td{border:1px solid #ccc;padding:.3rem .6rem;text-align:left;vertical-align:top;white-space:pre-wrap}\
+th{background:#f4f4f4}\
+pre{background:#f8f8f8;border:1px solid #ddd;padding:.8rem;overflow-x:auto}";
+
+/// Renders `items` as a standalone HTML document with a table and any detail blocks.
+pub fn render<T: Tabular>(title: &str, items: &[T]) -> String {
+    let mut body = String::from("<table>\n<tr>");
+    for header in T::HEADERS {
+        body.push_str(&format!("<th>{}</th>", escape(header)));
+    }
+    body.push_str("</tr>\n");
+    for row in items.iter().flat_map(Tabular::rows) {
+        body.push_str("<tr>");
+        for cell in row {
+            body.push_str(&format!("<td>{}</td>", escape(&cell)));
+        }
+        body.push_str("</tr>\n");
+    }
+    body.push_str("</table>\n");
+
+    for detail in items.iter().filter_map(Tabular::detail) {
+        body.push_str(&format!("<pre>{}</pre>\n", escape(&detail)));
+    }
+
+    document(title, STYLE, &body)
+}
+
+/// Wraps `body` into a complete HTML document.
+pub fn document(title: &str, style: &str, body: &str) -> String {
+    format!(
+        "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n<title>{title}</title>\n<style>{style}</style>\n</head>\n<body>\n<h1>{title}</h1>\n{body}</body>\n</html>\n",
+        title = escape(title),
+        style = style,
+        body = body
+    )
+}
+
+pub fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
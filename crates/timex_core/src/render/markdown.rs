@@ -0,0 +1,43 @@
+use super::Tabular;
+
+/// Renders `items` as a Markdown table, followed by any detail blocks as fenced code.
+pub fn render<T: Tabular>(title: &str, items: &[T]) -> String {
+    let mut out = format!("# {}\n\n", title);
+
+    out.push_str(&format!("| {} |\n", T::HEADERS.join(" | ")));
+    out.push_str(&format!("|{}\n", " --- |".repeat(T::HEADERS.len())));
+    for row in items.iter().flat_map(Tabular::rows) {
+        let cells: Vec<String> = row.iter().map(|cell| escape(cell)).collect();
+        out.push_str(&format!("| {} |\n", cells.join(" | ")));
+    }
+
+    for detail in items.iter().filter_map(Tabular::detail) {
+        let fence = fence(&detail);
+        out.push_str(&format!("\n{fence}diff\n{detail}{fence}\n"));
+    }
+    out
+}
+
+/// A code fence longer than any run of backticks in `content`, so that diffs of Markdown files
+/// cannot close it early.
+fn fence(content: &str) -> String {
+    let longest = content.split(|c| c != '`').map(str::len).max().unwrap_or(0);
+    "`".repeat(longest.max(2) + 1)
+}
+
+/// Keeps cell content on one line and stops pipes from splitting cells.
+fn escape(cell: &str) -> String {
+    cell.replace('|', "\\|").replace('\n', "<br>")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fence_outlasts_backticks_in_content() {
+        assert_eq!(fence("+let x = 1;\n"), "```");
+        assert_eq!(fence("+Run `cargo test`\n"), "```");
+        assert_eq!(fence("-```rust\n+````rust\n"), "`````");
+    }
+}
@@ -0,0 +1,206 @@
+pub mod csv;
+pub mod diff_html;
+pub mod highlight;
+pub mod html;
+pub mod markdown;
+
+use crate::git::diff::CommitDiff;
+use crate::git::GitCommit;
+use crate::project::TimeEntry;
+use crate::summary::ProjectSummary;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, thiserror::Error)]
+pub enum RenderError {
+    #[error("Failed to serialize JSON: {0}")]
+    Json(#[from] serde_json::Error),
+}
+
+/// The structured formats reports, timesheets and diffs can be exported as.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExportFormat {
+    Json,
+    Csv,
+    Markdown,
+    /// A standalone HTML document with inline styles.
+    Html,
+}
+
+/// A record that can be laid out as rows of a table.
+pub trait Tabular {
+    const HEADERS: &'static [&'static str];
+
+    /// The table rows for this record; a record may span several rows.
+    fn rows(&self) -> Vec<Vec<String>>;
+
+    /// Preformatted text shown below the table in document formats, e.g. a diff.
+    fn detail(&self) -> Option<String> {
+        None
+    }
+}
+
+/// Renders `items` in `format`, using `title` as the document heading where the format has one.
+pub fn render<T: Serialize + Tabular>(title: &str, items: &[T], format: ExportFormat) -> Result<String, RenderError> {
+    Ok(match format {
+        ExportFormat::Json => serde_json::to_string_pretty(items)? + "\n",
+        ExportFormat::Csv => csv::render(items),
+        ExportFormat::Markdown => markdown::render(title, items),
+        ExportFormat::Html => html::render(title, items),
+    })
+}
+
+impl Tabular for GitCommit {
+    const HEADERS: &'static [&'static str] =
+        &["hash", "author", "email", "author_date", "committer_date", "category", "co_authors", "title"];
+
+    fn rows(&self) -> Vec<Vec<String>> {
+        vec![vec![
+            self.hash.clone(),
+            self.author.clone(),
+            self.author_email.clone(),
+            self.author_date.to_rfc3339(),
+            self.committer_date.to_rfc3339(),
+            self.category().to_string(),
+            self.trailers
+                .co_authors
+                .iter()
+                .map(|person| person.name.as_str())
+                .collect::<Vec<_>>()
+                .join("; "),
+            self.title.clone(),
+        ]]
+    }
+}
+
+impl Tabular for CommitDiff {
+    const HEADERS: &'static [&'static str] =
+        &["commit", "path", "change", "source_path", "added", "removed", "attributes", "content"];
+
+    fn rows(&self) -> Vec<Vec<String>> {
+        self.changes
+            .iter()
+            .map(|change| {
+                vec![
+                    self.new_commit.clone(),
+                    change.path.clone(),
+                    change.change_type.name().to_string(),
+                    change.source_path.clone().unwrap_or_default(),
+                    change.lines_added.to_string(),
+                    change.lines_removed.to_string(),
+                    change.attributes.labels().join(" "),
+                    change.content.kind().to_string(),
+                ]
+            })
+            .collect()
+    }
+
+    fn detail(&self) -> Option<String> {
+        Some(self.to_plain_text())
+    }
+}
+
+impl Tabular for TimeEntry {
+    const HEADERS: &'static [&'static str] = &[
+        "project",
+        "task",
+        "start",
+        "end",
+        "hours",
+        "description",
+        "tags",
+        "billable",
+        "source",
+        "confidence",
+        "provenance",
+    ];
+
+    fn rows(&self) -> Vec<Vec<String>> {
+        vec![vec![
+            self.project.clone(),
+            self.task.clone().unwrap_or_default(),
+            self.start_time.to_rfc3339(),
+            self.end_time.to_rfc3339(),
+            format!("{:.2}", self.duration().num_seconds() as f64 / 3600.0),
+            self.description.clone(),
+            self.tags.join("; "),
+            self.billable.to_string(),
+            self.source.to_string(),
+            format!("{:.2}", self.confidence),
+            self.provenance.to_string(),
+        ]]
+    }
+}
+
+impl Tabular for ProjectSummary {
+    const HEADERS: &'static [&'static str] = &["project", "code", "summary"];
+
+    fn rows(&self) -> Vec<Vec<String>> {
+        vec![vec![self.project.clone(), self.code.clone(), self.summary.clone()]]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::project::{EntrySource, Provenance};
+    use chrono::{TimeZone, Utc};
+
+    fn entries() -> Vec<TimeEntry> {
+        vec![TimeEntry {
+            project: "TIMEX_001".into(),
+            task: Some("ABC-1".into()),
+            start_time: Utc.with_ymd_and_hms(2024, 1, 1, 9, 0, 0).unwrap(),
+            end_time: Utc.with_ymd_and_hms(2024, 1, 1, 12, 30, 0).unwrap(),
+            utc_offset: None,
+            description: "Login, signup".into(),
+            tags: vec!["frontend".into(), "auth".into()],
+            billable: false,
+            source: EntrySource::Imported,
+            confidence: 0.75,
+            provenance: Provenance {
+                origin: Some("jira#42".into()),
+                adjusted_by: Vec::new(),
+            },
+        }]
+    }
+
+    #[test]
+    fn test_render_time_entries_as_csv() {
+        let csv = render("Timesheet", &entries(), ExportFormat::Csv).unwrap();
+        assert_eq!(
+            csv,
+            "project,task,start,end,hours,description,tags,billable,source,confidence,provenance\n\
+             TIMEX_001,ABC-1,2024-01-01T09:00:00+00:00,2024-01-01T12:30:00+00:00,3.50,\"Login, signup\",\
+             frontend; auth,false,imported,0.75,jira#42\n"
+        );
+    }
+
+    #[test]
+    fn test_json_round_trips() {
+        let json = render("Timesheet", &entries(), ExportFormat::Json).unwrap();
+        let parsed: Vec<TimeEntry> = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed, entries());
+
+        let minimal: TimeEntry = serde_json::from_str(
+            r#"{"project": "P1", "start_time": "2024-01-01T09:00:00Z", "end_time": "2024-01-01T10:00:00Z"}"#,
+        )
+        .unwrap();
+        assert!(minimal.billable && minimal.confidence == 1.0);
+        assert_eq!(minimal.source, EntrySource::Inferred);
+    }
+
+    #[test]
+    fn test_html_is_standalone_and_escaped() {
+        let summaries = vec![ProjectSummary {
+            project: "Timex".into(),
+            code: "T1".into(),
+            summary: "Fixed <script> injection".into(),
+        }];
+        let html = render("Report", &summaries, ExportFormat::Html).unwrap();
+
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert!(html.contains("Fixed &lt;script&gt; injection"));
+        assert!(html.trim_end().ends_with("</html>"));
+    }
+}
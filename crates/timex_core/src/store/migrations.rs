@@ -0,0 +1,107 @@
+use super::StoreError;
+use rusqlite::Connection;
+
+/// Schema changes in the order they were introduced; a database at version `n` has had the first
+/// `n` applied. Released entries must never be edited or reordered, only appended to, so that
+/// existing stores upgrade in place and keep their history.
+const MIGRATIONS: &[&str] = &[
+    // 1: commits, per-file statistics, time entries and cached summaries.
+    "
+    CREATE TABLE commits (
+        project_code    TEXT NOT NULL,
+        hash            TEXT NOT NULL,
+        title           TEXT NOT NULL,
+        body            TEXT,
+        author          TEXT NOT NULL,
+        author_email    TEXT NOT NULL,
+        committer       TEXT NOT NULL,
+        committer_email TEXT NOT NULL,
+        author_date     TEXT NOT NULL,
+        committer_date  TEXT NOT NULL,
+        committer_time  INTEGER NOT NULL,
+        PRIMARY KEY (project_code, hash)
+    );
+
+    CREATE TABLE file_stats (
+        project_code  TEXT NOT NULL,
+        commit_hash   TEXT NOT NULL,
+        parent_hash   TEXT NOT NULL,
+        path          TEXT NOT NULL,
+        change_type   TEXT NOT NULL,
+        source_path   TEXT,
+        lines_added   INTEGER NOT NULL,
+        lines_removed INTEGER NOT NULL,
+        similarity    REAL,
+        PRIMARY KEY (project_code, commit_hash, parent_hash, path)
+    );
+
+    CREATE TABLE time_entries (
+        project_code TEXT NOT NULL,
+        start_time   TEXT NOT NULL,
+        end_time     TEXT NOT NULL,
+        PRIMARY KEY (project_code, start_time)
+    );
+
+    CREATE TABLE summaries (
+        content_hash TEXT PRIMARY KEY,
+        summary      TEXT NOT NULL,
+        created_at   TEXT NOT NULL
+    );
+    ",
+    // 2: files marked generated, vendored or binary by `.gitattributes`.
+    "
+    ALTER TABLE file_stats ADD COLUMN generated INTEGER NOT NULL DEFAULT 0;
+    ALTER TABLE file_stats ADD COLUMN vendored  INTEGER NOT NULL DEFAULT 0;
+    ALTER TABLE file_stats ADD COLUMN binary    INTEGER NOT NULL DEFAULT 0;
+    ",
+    // 3: binary and LFS content, as the JSON of a `FileContent`.
+    r#"
+    ALTER TABLE file_stats ADD COLUMN content TEXT NOT NULL DEFAULT '{"kind":"text"}';
+    "#,
+    // 4: the ticket a time entry was spent on.
+    "
+    ALTER TABLE time_entries ADD COLUMN task TEXT;
+    ",
+    // 5: time entry details, with tags as a JSON array.
+    "
+    ALTER TABLE time_entries ADD COLUMN description TEXT NOT NULL DEFAULT '';
+    ALTER TABLE time_entries ADD COLUMN tags        TEXT NOT NULL DEFAULT '[]';
+    ALTER TABLE time_entries ADD COLUMN billable    INTEGER NOT NULL DEFAULT 1;
+    ALTER TABLE time_entries ADD COLUMN source      TEXT NOT NULL DEFAULT 'inferred';
+    ALTER TABLE time_entries ADD COLUMN confidence  REAL NOT NULL DEFAULT 1;
+    ",
+    // 6: where a time entry came from, as the JSON of a `Provenance`.
+    "
+    ALTER TABLE time_entries ADD COLUMN provenance TEXT NOT NULL DEFAULT '{}';
+    ",
+    // 7: the UTC offset, in seconds, the work of a time entry was done at.
+    "
+    ALTER TABLE time_entries ADD COLUMN utc_offset INTEGER;
+    ",
+];
+
+/// The schema version this build reads and writes.
+pub const SCHEMA_VERSION: u32 = MIGRATIONS.len() as u32;
+
+/// Brings the database up to [`SCHEMA_VERSION`], one transaction per migration.
+pub(super) fn migrate(conn: &mut Connection) -> Result<(), StoreError> {
+    let found = schema_version(conn)?;
+    if found > SCHEMA_VERSION {
+        return Err(StoreError::UnsupportedSchema {
+            found,
+            supported: SCHEMA_VERSION,
+        });
+    }
+
+    for (version, migration) in MIGRATIONS.iter().enumerate().skip(found as usize) {
+        let tx = conn.transaction()?;
+        tx.execute_batch(migration)?;
+        tx.pragma_update(None, "user_version", version as u32 + 1)?;
+        tx.commit()?;
+    }
+    Ok(())
+}
+
+pub(super) fn schema_version(conn: &Connection) -> Result<u32, StoreError> {
+    Ok(conn.pragma_query_value(None, "user_version", |row| row.get(0))?)
+}
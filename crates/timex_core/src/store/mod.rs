@@ -0,0 +1,461 @@
+pub mod migrations;
+
+use crate::git::attributes::FileAttributes;
+use crate::git::diff::{ChangeType, CommitDiff, FileChange, FileContent};
+use crate::git::GitCommit;
+use crate::project::{EntrySource, TimeEntry};
+use crate::summary::ProjectActivity;
+use chrono::{DateTime, SecondsFormat, Utc};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::sync::{Mutex, MutexGuard};
+
+pub use migrations::SCHEMA_VERSION;
+
+#[derive(Debug, thiserror::Error)]
+pub enum StoreError {
+    #[error("SQLite error: {0}")]
+    Sqlite(#[from] rusqlite::Error),
+    #[error("Failed to create the store directory: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Invalid timestamp in store: {0}")]
+    Timestamp(#[from] chrono::ParseError),
+    #[error("Invalid value in store: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("Store has schema version {found}, but this build only supports up to {supported}")]
+    UnsupportedSchema { found: u32, supported: u32 },
+}
+
+/// Line statistics for one file changed by a commit, as kept in the store.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FileStat {
+    pub commit: String,
+    pub parent: String,
+    pub path: String,
+    pub change_type: ChangeType,
+    pub source_path: Option<String>,
+    pub lines_added: usize,
+    pub lines_removed: usize,
+    pub similarity: Option<f32>,
+    pub attributes: FileAttributes,
+    pub content: FileContent,
+}
+
+/// An embedded SQLite database of everything derived from the projects' histories.
+///
+/// Commits and statistics are keyed by project code, so one store can serve several workloads.
+pub struct Store {
+    conn: Mutex<Connection>,
+}
+
+impl Store {
+    /// Opens or creates the store at `path`, upgrading its schema if it predates this build.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, StoreError> {
+        if let Some(parent) = path.as_ref().parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        Self::with_connection(Connection::open(path)?)
+    }
+
+    /// A store that lives only as long as the returned value.
+    pub fn open_in_memory() -> Result<Self, StoreError> {
+        Self::with_connection(Connection::open_in_memory()?)
+    }
+
+    fn with_connection(mut conn: Connection) -> Result<Self, StoreError> {
+        migrations::migrate(&mut conn)?;
+        Ok(Store { conn: Mutex::new(conn) })
+    }
+
+    fn conn(&self) -> MutexGuard<'_, Connection> {
+        // A panic mid-statement leaves nothing half-written outside a transaction, so the
+        // connection stays usable.
+        self.conn.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    pub fn schema_version(&self) -> Result<u32, StoreError> {
+        migrations::schema_version(&self.conn())
+    }
+
+    /// Stores the commits of `activity` and the file statistics of its diffs.
+    pub fn save_activity(&self, activity: &ProjectActivity) -> Result<(), StoreError> {
+        let mut conn = self.conn();
+        let tx = conn.transaction()?;
+        insert_commits(&tx, &activity.project.code, &activity.commits)?;
+        for diff in &activity.diffs {
+            insert_file_stats(&tx, &activity.project.code, diff)?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Stores `commits` under `project_code`, replacing any already stored with the same hash.
+    pub fn save_commits(&self, project_code: &str, commits: &[GitCommit]) -> Result<(), StoreError> {
+        let mut conn = self.conn();
+        let tx = conn.transaction()?;
+        insert_commits(&tx, project_code, commits)?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// The stored commits of `project_code`, newest first.
+    pub fn commits(&self, project_code: &str) -> Result<Vec<GitCommit>, StoreError> {
+        let conn = self.conn();
+        let mut statement = conn.prepare(
+            "SELECT hash, title, body, author, author_email, committer, committer_email, author_date, committer_date
+             FROM commits WHERE project_code = ?1 ORDER BY committer_time DESC, hash",
+        )?;
+        let rows = statement.query_map([project_code], |row| {
+            Ok((
+                GitCommit {
+                    hash: row.get(0)?,
+                    title: row.get(1)?,
+                    body: row.get(2)?,
+                    author: row.get(3)?,
+                    author_email: row.get(4)?,
+                    committer: row.get(5)?,
+                    committer_email: row.get(6)?,
+                    author_date: Default::default(),
+                    committer_date: Default::default(),
+                    conventional: None,
+                    trailers: Default::default(),
+                },
+                row.get::<_, String>(7)?,
+                row.get::<_, String>(8)?,
+            ))
+        })?;
+
+        rows.map(|row| {
+            let (mut commit, author_date, committer_date) = row?;
+            commit.author_date = DateTime::parse_from_rfc3339(&author_date)?;
+            commit.committer_date = DateTime::parse_from_rfc3339(&committer_date)?;
+            commit.parse_message();
+            Ok(commit)
+        })
+        .collect()
+    }
+
+    /// The stored statistics of the files `commit_hash` changed in `project_code`.
+    pub fn file_stats(&self, project_code: &str, commit_hash: &str) -> Result<Vec<FileStat>, StoreError> {
+        let conn = self.conn();
+        let mut statement = conn.prepare(
+            "SELECT commit_hash, parent_hash, path, change_type, source_path, lines_added, lines_removed, similarity,
+                    generated, vendored, binary, content
+             FROM file_stats WHERE project_code = ?1 AND commit_hash = ?2 ORDER BY parent_hash, path",
+        )?;
+        let rows = statement.query_map([project_code, commit_hash], |row| {
+            Ok((
+                FileStat {
+                    commit: row.get(0)?,
+                    parent: row.get(1)?,
+                    path: row.get(2)?,
+                    change_type: ChangeType::Modified,
+                    source_path: row.get(4)?,
+                    lines_added: row.get(5)?,
+                    lines_removed: row.get(6)?,
+                    similarity: row.get(7)?,
+                    attributes: FileAttributes {
+                        generated: row.get(8)?,
+                        vendored: row.get(9)?,
+                        binary: row.get(10)?,
+                    },
+                    content: FileContent::Text,
+                },
+                row.get::<_, String>(3)?,
+                row.get::<_, String>(11)?,
+            ))
+        })?;
+
+        rows.map(|row| {
+            let (mut stat, change_type, content) = row?;
+            stat.change_type = serde_json::from_value(serde_json::Value::String(change_type))?;
+            stat.content = serde_json::from_str(&content)?;
+            Ok(stat)
+        })
+        .collect()
+    }
+
+    /// The stored diffs `commit_hash` introduced in `project_code`, one per parent, with line
+    /// counts but without hunks.
+    pub fn commit_diffs(&self, project_code: &str, commit_hash: &str) -> Result<Vec<CommitDiff>, StoreError> {
+        let mut diffs: Vec<CommitDiff> = Vec::new();
+        for stat in self.file_stats(project_code, commit_hash)? {
+            if diffs.last().is_none_or(|diff| diff.old_commit != stat.parent) {
+                diffs.push(CommitDiff {
+                    old_commit: stat.parent.clone(),
+                    new_commit: stat.commit.clone(),
+                    changes: Vec::new(),
+                });
+            }
+            diffs.last_mut().expect("pushed above").changes.push(FileChange {
+                path: stat.path,
+                change_type: stat.change_type,
+                lines_added: stat.lines_added,
+                lines_removed: stat.lines_removed,
+                source_path: stat.source_path,
+                similarity: stat.similarity,
+                hunks: Vec::new(),
+                attributes: stat.attributes,
+                content: stat.content,
+            });
+        }
+        Ok(diffs)
+    }
+
+    /// Stores `entries`, dropping previously stored entries of the same projects that they overlap.
+    ///
+    /// Re-inferring a period therefore replaces its sessions instead of piling up near-duplicates
+    /// whenever a session grows by a commit.
+    pub fn save_time_entries(&self, entries: &[TimeEntry]) -> Result<(), StoreError> {
+        let mut conn = self.conn();
+        let tx = conn.transaction()?;
+        for entry in entries {
+            let (start, end) = (timestamp(&entry.start_time), timestamp(&entry.end_time));
+            tx.execute(
+                "DELETE FROM time_entries
+                 WHERE project_code = ?1 AND (start_time = ?2 OR (start_time < ?3 AND end_time > ?2))",
+                params![entry.project, start, end],
+            )?;
+            tx.execute(
+                "INSERT INTO time_entries
+                 (project_code, start_time, end_time, task, description, tags, billable, source, confidence, provenance,
+                  utc_offset)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+                params![
+                    entry.project,
+                    start,
+                    end,
+                    entry.task,
+                    entry.description,
+                    serde_json::to_string(&entry.tags)?,
+                    entry.billable,
+                    entry.source.to_string(),
+                    entry.confidence,
+                    serde_json::to_string(&entry.provenance)?,
+                    entry.utc_offset,
+                ],
+            )?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// The stored time entries of the project with `project_code`, oldest first.
+    pub fn time_entries(&self, project_code: &str) -> Result<Vec<TimeEntry>, StoreError> {
+        let conn = self.conn();
+        let mut statement = conn.prepare(
+            "SELECT start_time, end_time, task, description, tags, billable, source, confidence, provenance, utc_offset
+             FROM time_entries WHERE project_code = ?1 ORDER BY start_time",
+        )?;
+        let rows = statement.query_map([project_code], |row| {
+            Ok((
+                TimeEntry {
+                    project: project_code.to_string(),
+                    task: row.get(2)?,
+                    start_time: Default::default(),
+                    end_time: Default::default(),
+                    utc_offset: row.get(9)?,
+                    description: row.get(3)?,
+                    tags: Vec::new(),
+                    billable: row.get(5)?,
+                    source: EntrySource::Inferred,
+                    confidence: row.get(7)?,
+                    provenance: Default::default(),
+                },
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, String>(4)?,
+                row.get::<_, String>(6)?,
+                row.get::<_, String>(8)?,
+            ))
+        })?;
+
+        rows.map(|row| {
+            let (mut entry, start, end, tags, source, provenance) = row?;
+            entry.start_time = DateTime::parse_from_rfc3339(&start)?.to_utc();
+            entry.end_time = DateTime::parse_from_rfc3339(&end)?.to_utc();
+            entry.tags = serde_json::from_str(&tags)?;
+            entry.source = serde_json::from_value(serde_json::Value::String(source))?;
+            entry.provenance = serde_json::from_str(&provenance)?;
+            Ok(entry)
+        })
+        .collect()
+    }
+
+    /// The cached summary of the content hashing to `content_hash`, if any.
+    pub fn summary(&self, content_hash: &str) -> Result<Option<String>, StoreError> {
+        Ok(self
+            .conn()
+            .query_row(
+                "SELECT summary FROM summaries WHERE content_hash = ?1",
+                [content_hash],
+                |row| row.get(0),
+            )
+            .optional()?)
+    }
+
+    pub fn save_summary(&self, content_hash: &str, summary: &str) -> Result<(), StoreError> {
+        self.conn().execute(
+            "INSERT OR REPLACE INTO summaries (content_hash, summary, created_at) VALUES (?1, ?2, ?3)",
+            params![content_hash, summary, timestamp(&Utc::now())],
+        )?;
+        Ok(())
+    }
+}
+
+fn insert_commits(conn: &Connection, project_code: &str, commits: &[GitCommit]) -> Result<(), StoreError> {
+    let mut statement = conn.prepare_cached(
+        "INSERT OR REPLACE INTO commits
+         (project_code, hash, title, body, author, author_email, committer, committer_email,
+          author_date, committer_date, committer_time)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+    )?;
+    for commit in commits {
+        statement.execute(params![
+            project_code,
+            commit.hash,
+            commit.title,
+            commit.body,
+            commit.author,
+            commit.author_email,
+            commit.committer,
+            commit.committer_email,
+            commit.author_date.to_rfc3339(),
+            commit.committer_date.to_rfc3339(),
+            commit.committer_date.timestamp(),
+        ])?;
+    }
+    Ok(())
+}
+
+fn insert_file_stats(conn: &Connection, project_code: &str, diff: &CommitDiff) -> Result<(), StoreError> {
+    let mut statement = conn.prepare_cached(
+        "INSERT OR REPLACE INTO file_stats
+         (project_code, commit_hash, parent_hash, path, change_type, source_path, lines_added, lines_removed, similarity,
+          generated, vendored, binary, content)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)",
+    )?;
+    for change in &diff.changes {
+        let change_type = serde_json::to_value(change.change_type)?;
+        statement.execute(params![
+            project_code,
+            diff.new_commit,
+            diff.old_commit,
+            change.path,
+            change_type.as_str(),
+            change.source_path,
+            change.lines_added,
+            change.lines_removed,
+            change.similarity,
+            change.attributes.generated,
+            change.attributes.vendored,
+            change.attributes.binary,
+            serde_json::to_string(&change.content)?,
+        ])?;
+    }
+    Ok(())
+}
+
+/// UTC timestamps in a fixed-width form, so that they compare correctly as text.
+fn timestamp(time: &DateTime<Utc>) -> String {
+    time.to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git::diff::get_commit_diff;
+    use crate::git::test_repo::TestRepo;
+    use crate::project::{Project, Provenance};
+    use chrono::TimeZone;
+
+    fn project() -> Project {
+        Project::for_test("P1", "/tmp/project1")
+    }
+
+    fn entry(start_hour: u32, end_hour: u32) -> TimeEntry {
+        project().time_entry(
+            None,
+            Utc.with_ymd_and_hms(2024, 1, 1, start_hour, 0, 0).unwrap(),
+            Utc.with_ymd_and_hms(2024, 1, 1, end_hour, 0, 0).unwrap(),
+            EntrySource::Inferred,
+        )
+    }
+
+    #[test]
+    fn test_reopening_keeps_schema_and_data() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("nested").join("timex.db");
+
+        Store::open(&path).unwrap().save_summary("abc", "Did things").unwrap();
+        let store = Store::open(&path).unwrap();
+
+        assert_eq!(store.schema_version().unwrap(), SCHEMA_VERSION);
+        assert_eq!(store.summary("abc").unwrap().as_deref(), Some("Did things"));
+        assert_eq!(store.summary("def").unwrap(), None);
+    }
+
+    #[test]
+    fn test_newer_schema_is_rejected() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("timex.db");
+        Connection::open(&path)
+            .unwrap()
+            .pragma_update(None, "user_version", SCHEMA_VERSION + 1)
+            .unwrap();
+
+        assert!(matches!(Store::open(&path), Err(StoreError::UnsupportedSchema { .. })));
+    }
+
+    #[test]
+    fn test_activity_round_trip() {
+        let repo = TestRepo::new();
+        let first = repo.commit("a.txt", "one\n", "Add a");
+        let second = repo.commit("a.txt", "one\ntwo\n", "Extend a");
+        let git = repo.open();
+        let diff = get_commit_diff(&git, &first, &second, &Default::default()).unwrap();
+        let id = gix::ObjectId::from_hex(second.as_bytes()).unwrap();
+        let commits = vec![GitCommit::try_from(&git.find_commit(id).unwrap()).unwrap()];
+
+        let store = Store::open_in_memory().unwrap();
+        store
+            .save_activity(&ProjectActivity {
+                project: project(),
+                commits: commits.clone(),
+                diffs: vec![diff],
+            })
+            .unwrap();
+
+        assert_eq!(store.commits("P1").unwrap(), commits);
+        let stats = store.file_stats("P1", &second).unwrap();
+        assert_eq!(stats.len(), 1);
+        assert_eq!((stats[0].path.as_str(), stats[0].change_type), ("a.txt", ChangeType::Modified));
+        assert_eq!((stats[0].lines_added, stats[0].lines_removed), (1, 0));
+        let diffs = store.commit_diffs("P1", &second).unwrap();
+        assert_eq!((diffs[0].old_commit.as_str(), diffs[0].changes[0].lines_added), (first.as_str(), 1));
+        assert!(diffs[0].changes[0].hunks.is_empty());
+    }
+
+    #[test]
+    fn test_saving_time_entries_replaces_overlapping_ones() {
+        let store = Store::open_in_memory().unwrap();
+        store.save_time_entries(&[entry(9, 10), entry(13, 14)]).unwrap();
+        let ticketed = TimeEntry {
+            task: Some("ABC-1".into()),
+            tags: vec!["backend".into()],
+            billable: false,
+            source: EntrySource::Manual,
+            confidence: 0.5,
+            utc_offset: Some(9 * 3600),
+            provenance: Provenance {
+                origin: Some("entries.toml#1".into()),
+                adjusted_by: Vec::new(),
+            },
+            ..entry(9, 11)
+        };
+        store.save_time_entries(std::slice::from_ref(&ticketed)).unwrap();
+
+        assert_eq!(store.time_entries("P1").unwrap(), vec![ticketed, entry(13, 14)]);
+    }
+}
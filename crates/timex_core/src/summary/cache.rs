@@ -0,0 +1,101 @@
+use super::chunk::activity_sections;
+use super::{ProjectActivity, SummaryError, Summarizer};
+use crate::store::Store;
+use async_trait::async_trait;
+use sha2::{Digest, Sha256};
+
+/// Serves summaries from a [`Store`], only asking the wrapped summarizer about activity it has
+/// not summarised before.
+pub struct CachedSummarizer<'a> {
+    inner: &'a dyn Summarizer,
+    store: &'a Store,
+}
+
+impl<'a> CachedSummarizer<'a> {
+    pub fn new(inner: &'a dyn Summarizer, store: &'a Store) -> Self {
+        CachedSummarizer { inner, store }
+    }
+}
+
+/// A hex SHA-256 over everything that determines a summary: the summarizer, the project and the
+/// commits and diffs of `activity`.
+pub fn content_hash(cache_key: &str, activity: &ProjectActivity) -> String {
+    let mut hasher = Sha256::new();
+    for part in [cache_key, &activity.project.name, &activity.project.code] {
+        hasher.update(part.as_bytes());
+        hasher.update([0]);
+    }
+    for commit in &activity.commits {
+        hasher.update(commit.hash.as_bytes());
+        hasher.update([0]);
+    }
+    for section in activity_sections(activity) {
+        hasher.update(section.as_bytes());
+        hasher.update([0]);
+    }
+    format!("{:x}", hasher.finalize())
+}
+
+#[async_trait]
+impl Summarizer for CachedSummarizer<'_> {
+    fn cache_key(&self) -> String {
+        self.inner.cache_key()
+    }
+
+    async fn summarize(&self, activity: &ProjectActivity) -> Result<String, SummaryError> {
+        let hash = content_hash(&self.inner.cache_key(), activity);
+        if let Some(summary) = self.store.summary(&hash)? {
+            return Ok(summary);
+        }
+        let summary = self.inner.summarize(activity).await?;
+        self.store.save_summary(&hash, &summary)?;
+        Ok(summary)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::project::Project;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[derive(Default)]
+    struct CountingSummarizer {
+        calls: AtomicUsize,
+    }
+
+    #[async_trait]
+    impl Summarizer for CountingSummarizer {
+        fn cache_key(&self) -> String {
+            "counting".into()
+        }
+
+        async fn summarize(&self, activity: &ProjectActivity) -> Result<String, SummaryError> {
+            let call = self.calls.fetch_add(1, Ordering::SeqCst);
+            Ok(format!("{} #{}", activity.project.name, call))
+        }
+    }
+
+    fn activity(name: &str) -> ProjectActivity {
+        ProjectActivity {
+            project: Project {
+                name: name.into(),
+                ..Project::for_test("C", "")
+            },
+            commits: Vec::new(),
+            diffs: Vec::new(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_unchanged_activity_is_summarised_once() {
+        let store = Store::open_in_memory().unwrap();
+        let inner = CountingSummarizer::default();
+        let cached = CachedSummarizer::new(&inner, &store);
+
+        assert_eq!(cached.summarize(&activity("A")).await.unwrap(), "A #0");
+        assert_eq!(cached.summarize(&activity("A")).await.unwrap(), "A #0");
+        assert_eq!(cached.summarize(&activity("B")).await.unwrap(), "B #1");
+        assert_eq!(inner.calls.load(Ordering::SeqCst), 2);
+    }
+}
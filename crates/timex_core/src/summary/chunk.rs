@@ -0,0 +1,157 @@
+use super::ProjectActivity;
+use lazy_static::lazy_static;
+use std::collections::HashMap;
+use tiktoken_rs::CoreBPE;
+
+/// Counts how many tokens a piece of text occupies in a model's context window.
+pub trait TokenCounter: Send + Sync {
+    fn count(&self, text: &str) -> usize;
+}
+
+/// Counts tokens with OpenAI's `cl100k_base` encoding, a close estimate for most chat models.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Cl100kCounter;
+
+impl TokenCounter for Cl100kCounter {
+    fn count(&self, text: &str) -> usize {
+        lazy_static! {
+            static ref BPE: CoreBPE = tiktoken_rs::cl100k_base().expect("cl100k_base is bundled");
+        }
+        BPE.encode_ordinary(text).len()
+    }
+}
+
+/// One plain-text section per diff of `activity`, headed by the title of the commit it belongs to.
+pub fn activity_sections(activity: &ProjectActivity) -> Vec<String> {
+    let titles: HashMap<&str, &str> = activity
+        .commits
+        .iter()
+        .map(|commit| (commit.hash.as_str(), commit.title.as_str()))
+        .collect();
+
+    activity
+        .diffs
+        .iter()
+        .map(|diff| {
+            let title = titles.get(diff.new_commit.as_str()).copied().unwrap_or_default();
+            format!("commit {}: {}\n{}", diff.new_commit, title, diff.to_plain_text())
+        })
+        .collect()
+}
+
+/// Packs `sections` into as few chunks as possible, each within `budget` tokens.
+///
+/// Sections are kept whole where they fit. Larger sections are split at line boundaries, and
+/// single lines that exceed the budget on their own are truncated.
+pub fn chunk_sections(sections: &[String], counter: &dyn TokenCounter, budget: usize) -> Vec<String> {
+    let mut chunks = Vec::new();
+    let mut current = String::new();
+    let mut current_tokens = 0;
+
+    for section in sections {
+        for piece in split_section(section, counter, budget) {
+            let tokens = counter.count(&piece);
+            if current_tokens + tokens > budget && !current.is_empty() {
+                chunks.push(std::mem::take(&mut current));
+                current_tokens = 0;
+            }
+            current.push_str(&piece);
+            if !piece.ends_with('\n') {
+                current.push('\n');
+            }
+            current_tokens += tokens;
+        }
+    }
+    if !current.is_empty() {
+        chunks.push(current);
+    }
+
+    chunks
+}
+
+fn split_section(section: &str, counter: &dyn TokenCounter, budget: usize) -> Vec<String> {
+    if counter.count(section) <= budget {
+        return vec![section.to_string()];
+    }
+
+    let mut pieces = Vec::new();
+    let mut current = String::new();
+    let mut current_tokens = 0;
+    for line in section.lines() {
+        let line = truncate_to_budget(line, counter, budget);
+        let tokens = counter.count(&line) + 1;
+        if current_tokens + tokens > budget && !current.is_empty() {
+            pieces.push(std::mem::take(&mut current));
+            current_tokens = 0;
+        }
+        current.push_str(&line);
+        current.push('\n');
+        current_tokens += tokens;
+    }
+    if !current.is_empty() {
+        pieces.push(current);
+    }
+    pieces
+}
+
+/// The longest prefix of `line` that fits into `budget` tokens, leaving room for the line break.
+///
+/// Prefixes are binary searched by character, counting tokens only a logarithmic number of times.
+pub(crate) fn truncate_to_budget(line: &str, counter: &dyn TokenCounter, budget: usize) -> String {
+    let budget = budget.saturating_sub(1);
+    if counter.count(line) <= budget {
+        return line.to_string();
+    }
+    let ends: Vec<usize> = line.char_indices().map(|(index, _)| index).collect();
+    let fitting = ends.partition_point(|&end| counter.count(&line[..end]) <= budget);
+    line[..ends[fitting.saturating_sub(1)]].to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// One token per whitespace-separated word, to keep expectations readable.
+    struct WordCounter;
+
+    impl TokenCounter for WordCounter {
+        fn count(&self, text: &str) -> usize {
+            text.split_whitespace().count()
+        }
+    }
+
+    #[test]
+    fn test_sections_are_packed_within_budget() {
+        let sections = ["a b c\n".to_string(), "d e\n".to_string(), "f g h i\n".to_string()];
+        let chunks = chunk_sections(&sections, &WordCounter, 5);
+
+        assert_eq!(chunks, vec!["a b c\nd e\n", "f g h i\n"]);
+    }
+
+    #[test]
+    fn test_oversized_section_is_split_by_line() {
+        let sections = ["one two\nthree four\nfive six\n".to_string()];
+        let chunks = chunk_sections(&sections, &WordCounter, 5);
+
+        assert_eq!(chunks, vec!["one two\nthree four\n", "five six\n"]);
+    }
+
+    #[test]
+    fn test_truncated_line_fills_the_budget() {
+        let line: String = (1..=40).map(|i| format!("w{i} ")).collect();
+        let truncated = truncate_to_budget(&line, &WordCounter, 11);
+        assert!(truncated.starts_with("w1 w2"), "{truncated}");
+        assert_eq!(WordCounter.count(&truncated), 10);
+
+        let line = "fn parse(input: &str) -> Result<Vec<Token>, Error> { todo!() } ".repeat(50);
+        let truncated = truncate_to_budget(&line, &Cl100kCounter, 101);
+        let tokens = Cl100kCounter.count(&truncated);
+        assert!((99..=100).contains(&tokens), "{tokens}");
+        assert_eq!(truncate_to_budget("short", &Cl100kCounter, 101), "short");
+    }
+
+    #[test]
+    fn test_cl100k_counts_tokens() {
+        assert_eq!(Cl100kCounter.count("hello world"), 2);
+    }
+}
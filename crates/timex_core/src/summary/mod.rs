@@ -0,0 +1,63 @@
+pub mod cache;
+pub mod chunk;
+pub mod offline;
+pub mod openai;
+
+use crate::git::diff::CommitDiff;
+use crate::git::GitCommit;
+use crate::project::Project;
+use crate::store::StoreError;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+
+pub use cache::CachedSummarizer;
+pub use offline::OfflineSummarizer;
+pub use openai::{LlmConfig, OpenAISummarizer};
+
+#[derive(Debug, thiserror::Error)]
+pub enum SummaryError {
+    #[error("OpenAI API error: {0}")]
+    OpenAI(#[from] async_openai::error::OpenAIError),
+    #[error("Failed to build HTTP client: {0}")]
+    Http(#[from] reqwest::Error),
+    #[error("The model returned no summary")]
+    EmptyResponse,
+    #[error("Summary cache error: {0}")]
+    Store(#[from] StoreError),
+}
+
+/// A generated summary of the work on one project.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProjectSummary {
+    pub project: String,
+    pub code: String,
+    pub summary: String,
+}
+
+/// The user's commits on a project together with the diffs they introduced.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ProjectActivity {
+    pub project: Project,
+    pub commits: Vec<GitCommit>,
+    pub diffs: Vec<CommitDiff>,
+}
+
+/// A backend that turns a project's activity into a prose summary.
+#[async_trait]
+pub trait Summarizer: Send + Sync {
+    /// Identifies the backend and the settings that shape its output, so that cached summaries
+    /// are only reused by an equivalent summarizer.
+    fn cache_key(&self) -> String;
+
+    async fn summarize(&self, activity: &ProjectActivity) -> Result<String, SummaryError>;
+}
+
+/// Picks the backend for `config`: the configured endpoint, or offline summaries when `offline`
+/// is set or the public OpenAI API is configured without a key.
+pub fn summarizer_for(config: &LlmConfig, offline: bool) -> Result<Box<dyn Summarizer>, SummaryError> {
+    if offline || (config.is_default_endpoint() && config.api_key().is_none()) {
+        Ok(Box::new(OfflineSummarizer::default()))
+    } else {
+        Ok(Box::new(OpenAISummarizer::from_llm_config(config)?))
+    }
+}
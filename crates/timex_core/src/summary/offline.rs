@@ -0,0 +1,91 @@
+use super::{ProjectActivity, SummaryError, Summarizer};
+use crate::git::message::CommitCategory;
+use async_trait::async_trait;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write;
+
+/// Builds summaries from commit titles and file statistics, without any network access.
+///
+/// The output only depends on the activity passed in, so it is suitable for tests and for
+/// environments where code must not leave the machine.
+#[derive(Debug, Clone)]
+pub struct OfflineSummarizer {
+    /// How many of the most changed files to list.
+    pub max_files: usize,
+}
+
+impl Default for OfflineSummarizer {
+    fn default() -> Self {
+        OfflineSummarizer { max_files: 5 }
+    }
+}
+
+#[async_trait]
+impl Summarizer for OfflineSummarizer {
+    fn cache_key(&self) -> String {
+        format!("offline:{}", self.max_files)
+    }
+
+    async fn summarize(&self, activity: &ProjectActivity) -> Result<String, SummaryError> {
+        // Per-file (added, removed) totals, ordered by path so ties sort deterministically.
+        // Generated, vendored, binary and LFS files carry no line counts and are only counted.
+        let mut files: BTreeMap<&str, (usize, usize)> = BTreeMap::new();
+        let mut marked: BTreeSet<&str> = BTreeSet::new();
+        for change in activity.diffs.iter().flat_map(|diff| &diff.changes) {
+            if !change.is_diffed() {
+                marked.insert(change.path.as_str());
+                continue;
+            }
+            let stats = files.entry(change.path.as_str()).or_default();
+            stats.0 += change.lines_added;
+            stats.1 += change.lines_removed;
+        }
+        let (added, removed) = files
+            .values()
+            .fold((0, 0), |(a, r), (fa, fr)| (a + fa, r + fr));
+
+        let mut summary = String::new();
+        let _ = writeln!(
+            summary,
+            "{}: {} commits, {} files changed (+{}, -{})",
+            activity.project.name,
+            activity.commits.len(),
+            files.len(),
+            added,
+            removed
+        );
+        if !marked.is_empty() {
+            let _ = writeln!(summary, "{} generated, vendored, binary or LFS files changed", marked.len());
+        }
+
+        let mut categories: BTreeMap<CommitCategory, usize> = BTreeMap::new();
+        for commit in activity.commits.iter().filter(|commit| commit.conventional.is_some()) {
+            *categories.entry(commit.category()).or_default() += 1;
+        }
+        if !categories.is_empty() {
+            let counts: Vec<String> = categories.iter().map(|(category, n)| format!("{n} {category}")).collect();
+            let _ = writeln!(summary, "By category: {}", counts.join(", "));
+        }
+
+        for commit in &activity.commits {
+            let short = &commit.hash[..commit.hash.len().min(7)];
+            let co_authors: Vec<&str> = commit.trailers.co_authors.iter().map(|person| person.name.as_str()).collect();
+            if co_authors.is_empty() {
+                let _ = writeln!(summary, "- {} ({})", commit.title, short);
+            } else {
+                let _ = writeln!(summary, "- {} ({}, with {})", commit.title, short, co_authors.join(", "));
+            }
+        }
+
+        let mut ranked: Vec<_> = files.into_iter().collect();
+        ranked.sort_by_key(|(_, (a, r))| std::cmp::Reverse(a + r));
+        if !ranked.is_empty() {
+            let _ = writeln!(summary, "Most changed files:");
+        }
+        for (path, (a, r)) in ranked.into_iter().take(self.max_files) {
+            let _ = writeln!(summary, "- {} (+{}, -{})", path, a, r);
+        }
+
+        Ok(summary)
+    }
+}
@@ -0,0 +1,368 @@
+use super::chunk::{activity_sections, chunk_sections, truncate_to_budget, Cl100kCounter, TokenCounter};
+use super::{ProjectActivity, SummaryError, Summarizer};
+use async_openai::config::{Config, OpenAIConfig};
+use async_openai::types::{
+    ChatCompletionRequestMessage, ChatCompletionRequestUserMessage, CreateChatCompletionRequestArgs,
+};
+use async_openai::Client as OpenAIClient;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+pub const DEFAULT_BASE_URL: &str = "https://api.openai.com/v1";
+pub const DEFAULT_MODEL: &str = "gpt-4-turbo-preview";
+pub const DEFAULT_API_KEY_ENV: &str = "OPENAI_API_KEY";
+pub const DEFAULT_MAX_PROMPT_TOKENS: usize = 6000;
+
+const SUMMARY_PROMPT: &str =
+    "Please analyze these git commits and write a brief summary of the work done:";
+const MAP_PROMPT: &str =
+    "Here is one batch of the git commits made on a project. Summarize the work done in this batch:";
+const REDUCE_PROMPT: &str =
+    "Here are summaries of consecutive batches of work on a project. Combine them into a single brief summary of the work done:";
+
+/// The `[llm]` section of `workload.toml`, describing an OpenAI-compatible chat completion endpoint.
+///
+/// Any server implementing `/chat/completions` works, e.g. a self-hosted llama.cpp or vLLM instance.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct LlmConfig {
+    pub base_url: String,
+    pub model: String,
+    pub temperature: Option<f32>,
+    pub max_tokens: Option<u32>,
+    /// Request timeout in seconds.
+    pub timeout_secs: u64,
+    /// Name of the environment variable holding the API key.
+    pub api_key_env: String,
+    /// Token budget for the commit data in a single prompt; larger histories are summarised in batches.
+    pub max_prompt_tokens: usize,
+}
+
+impl Default for LlmConfig {
+    fn default() -> Self {
+        LlmConfig {
+            base_url: DEFAULT_BASE_URL.to_string(),
+            model: DEFAULT_MODEL.to_string(),
+            temperature: None,
+            max_tokens: None,
+            timeout_secs: 120,
+            api_key_env: DEFAULT_API_KEY_ENV.to_string(),
+            max_prompt_tokens: DEFAULT_MAX_PROMPT_TOKENS,
+        }
+    }
+}
+
+impl LlmConfig {
+    /// The API key from [`LlmConfig::api_key_env`], if it is set.
+    pub fn api_key(&self) -> Option<String> {
+        std::env::var(&self.api_key_env).ok()
+    }
+
+    /// Whether the endpoint is the public OpenAI API, which always needs a key.
+    pub fn is_default_endpoint(&self) -> bool {
+        self.base_url == DEFAULT_BASE_URL
+    }
+}
+
+/// Summarises activity with a chat completion model behind an OpenAI-compatible API.
+///
+/// Activity that doesn't fit into the prompt budget is split into batches that are summarised
+/// separately, after which the batch summaries are reduced into one project summary.
+pub struct OpenAISummarizer {
+    client: OpenAIClient<OpenAIConfig>,
+    base_url: String,
+    model: String,
+    temperature: Option<f32>,
+    max_tokens: Option<u32>,
+    max_prompt_tokens: usize,
+    counter: Box<dyn TokenCounter>,
+}
+
+impl OpenAISummarizer {
+    pub fn new(api_key: &str) -> Self {
+        Self::with_config(OpenAIConfig::default().with_api_key(api_key), DEFAULT_MODEL)
+    }
+
+    pub fn with_config(config: OpenAIConfig, model: impl Into<String>) -> Self {
+        OpenAISummarizer {
+            base_url: config.api_base().to_string(),
+            client: OpenAIClient::with_config(config),
+            model: model.into(),
+            temperature: None,
+            max_tokens: None,
+            max_prompt_tokens: DEFAULT_MAX_PROMPT_TOKENS,
+            counter: Box::new(Cl100kCounter),
+        }
+    }
+
+    /// Builds a summarizer for the endpoint described by `config`.
+    ///
+    /// A missing API key is not an error, as self-hosted servers usually don't check it.
+    pub fn from_llm_config(config: &LlmConfig) -> Result<Self, SummaryError> {
+        let openai_config = OpenAIConfig::default()
+            .with_api_base(&config.base_url)
+            .with_api_key(config.api_key().unwrap_or_default());
+        let http_client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(config.timeout_secs))
+            .build()?;
+
+        Ok(OpenAISummarizer {
+            client: OpenAIClient::with_config(openai_config).with_http_client(http_client),
+            base_url: config.base_url.clone(),
+            model: config.model.clone(),
+            temperature: config.temperature,
+            max_tokens: config.max_tokens,
+            max_prompt_tokens: config.max_prompt_tokens,
+            counter: Box::new(Cl100kCounter),
+        })
+    }
+
+    /// Replaces the tokenizer used to measure prompts, e.g. to match a self-hosted model.
+    pub fn with_token_counter(mut self, counter: impl TokenCounter + 'static) -> Self {
+        self.counter = Box::new(counter);
+        self
+    }
+
+    async fn complete(&self, instruction: &str, content: &str) -> Result<String, SummaryError> {
+        let messages = vec![ChatCompletionRequestMessage::User(ChatCompletionRequestUserMessage {
+            content: async_openai::types::ChatCompletionRequestUserMessageContent::Text(format!(
+                "{}\n{}",
+                instruction, content
+            )),
+            name: None,
+        })];
+
+        let mut request = CreateChatCompletionRequestArgs::default();
+        request.model(&self.model).messages(messages);
+        if let Some(temperature) = self.temperature {
+            request.temperature(temperature);
+        }
+        if let Some(max_tokens) = self.max_tokens {
+            // Deprecated by OpenAI, but it is the field self-hosted servers understand.
+            request.max_tokens(max_tokens);
+        }
+        let request = request.build()?;
+
+        let response = self.client.chat().create(request).await?;
+        response
+            .choices
+            .into_iter()
+            .next()
+            .and_then(|choice| choice.message.content)
+            .ok_or(SummaryError::EmptyResponse)
+    }
+
+    /// Repeatedly merges batch summaries until a single summary remains.
+    async fn reduce(&self, mut summaries: Vec<String>) -> Result<String, SummaryError> {
+        loop {
+            let chunks = chunk_sections(&summaries, self.counter.as_ref(), self.max_prompt_tokens);
+            if chunks.len() == 1 {
+                return self.complete(REDUCE_PROMPT, &chunks[0]).await;
+            }
+            if chunks.len() >= summaries.len() {
+                // Every summary fills a batch on its own, so merging can't make progress.
+                let joined = summaries.join("\n");
+                let truncated = truncate_to_budget(&joined, self.counter.as_ref(), self.max_prompt_tokens);
+                return self.complete(REDUCE_PROMPT, &truncated).await;
+            }
+
+            let mut reduced = Vec::with_capacity(chunks.len());
+            for chunk in &chunks {
+                reduced.push(self.complete(REDUCE_PROMPT, chunk).await?);
+            }
+            summaries = reduced;
+        }
+    }
+}
+
+#[async_trait]
+impl Summarizer for OpenAISummarizer {
+    fn cache_key(&self) -> String {
+        format!(
+            "openai:{}:{}:{:?}:{:?}:{}",
+            self.base_url, self.model, self.temperature, self.max_tokens, self.max_prompt_tokens
+        )
+    }
+
+    async fn summarize(&self, activity: &ProjectActivity) -> Result<String, SummaryError> {
+        let sections = activity_sections(activity);
+        let chunks = chunk_sections(&sections, self.counter.as_ref(), self.max_prompt_tokens);
+
+        match chunks.as_slice() {
+            [] => self.complete(SUMMARY_PROMPT, "").await,
+            [chunk] => self.complete(SUMMARY_PROMPT, chunk).await,
+            chunks => {
+                let mut summaries = Vec::with_capacity(chunks.len());
+                for chunk in chunks {
+                    summaries.push(self.complete(MAP_PROMPT, chunk).await?);
+                }
+                self.reduce(summaries).await
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::project::Project;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    /// Answers one request per entry of `replies` with a canned chat completion and returns the
+    /// JSON bodies of the requests it received.
+    async fn stub_server(replies: Vec<&'static str>) -> (String, tokio::task::JoinHandle<Vec<serde_json::Value>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}/v1", listener.local_addr().unwrap());
+
+        let handle = tokio::spawn(async move {
+            let mut requests = Vec::new();
+            for reply in replies {
+                requests.push(serve_one(&listener, reply).await);
+            }
+            requests
+        });
+
+        (base_url, handle)
+    }
+
+    async fn serve_one(listener: &TcpListener, reply: &str) -> serde_json::Value {
+        let (mut socket, _) = listener.accept().await.unwrap();
+        let mut request = Vec::new();
+        let mut buf = [0u8; 4096];
+        let body_start = loop {
+            let n = socket.read(&mut buf).await.unwrap();
+            request.extend_from_slice(&buf[..n]);
+            if let Some(pos) = request.windows(4).position(|w| w == b"\r\n\r\n") {
+                break pos + 4;
+            }
+        };
+        let headers = String::from_utf8_lossy(&request[..body_start]).to_lowercase();
+        let content_length: usize = headers
+            .lines()
+            .find_map(|line| line.strip_prefix("content-length:"))
+            .map(|len| len.trim().parse().unwrap())
+            .unwrap_or(0);
+        while request.len() < body_start + content_length {
+            let n = socket.read(&mut buf).await.unwrap();
+            request.extend_from_slice(&buf[..n]);
+        }
+
+        let body = serde_json::json!({
+            "id": "stub",
+            "object": "chat.completion",
+            "created": 0,
+            "model": "stub-model",
+            "choices": [{
+                "index": 0,
+                "message": { "role": "assistant", "content": reply },
+                "finish_reason": "stop"
+            }]
+        })
+        .to_string();
+        let response = format!(
+            "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+            body.len(),
+            body
+        );
+        socket.write_all(response.as_bytes()).await.unwrap();
+
+        serde_json::from_slice(&request[body_start..]).unwrap()
+    }
+
+    fn activity() -> ProjectActivity {
+        ProjectActivity {
+            project: Project::for_test("T1", "/tmp/test"),
+            commits: Vec::new(),
+            diffs: Vec::new(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_summarize_against_configured_endpoint() {
+        let (base_url, server) = stub_server(vec!["Worked on the stub."]).await;
+        let config: LlmConfig = toml::from_str(&format!(
+            r#"
+                base_url = "{base_url}"
+                model = "local-model"
+                temperature = 0.5
+                max_tokens = 256
+                timeout_secs = 5
+                api_key_env = "TIMEX_TEST_UNSET_KEY"
+            "#
+        ))
+        .unwrap();
+
+        let summarizer = OpenAISummarizer::from_llm_config(&config).unwrap();
+
+        let summary = summarizer.summarize(&activity()).await.unwrap();
+        let request = &server.await.unwrap()[0];
+
+        assert_eq!(summary, "Worked on the stub.");
+        assert_eq!(request["model"], "local-model");
+        assert_eq!(request["temperature"], 0.5);
+        assert_eq!(request["max_tokens"], 256);
+        assert!(request.get("max_completion_tokens").is_none());
+    }
+
+    #[test]
+    fn test_cache_key_names_the_endpoint_and_prompt_budget() {
+        let config = LlmConfig::default();
+        let key = |config: &LlmConfig| OpenAISummarizer::from_llm_config(config).unwrap().cache_key();
+        let other_endpoint = LlmConfig {
+            base_url: "http://localhost:8080/v1".into(),
+            ..config.clone()
+        };
+        let smaller_budget = LlmConfig {
+            max_prompt_tokens: 600,
+            ..config.clone()
+        };
+
+        assert_ne!(key(&config), key(&other_endpoint));
+        assert_ne!(key(&config), key(&smaller_budget));
+    }
+
+    #[tokio::test]
+    async fn test_large_activity_is_summarised_in_batches() {
+        use crate::git::test_repo::TestRepo;
+        use crate::project::workload::Workload;
+
+        let repo = TestRepo::new();
+        for i in 0..3 {
+            let content: String = (0..50).map(|n| format!("line {i} {n}\n")).collect();
+            repo.commit(&format!("file{i}.txt"), &content, &format!("Add file {i}"));
+        }
+        let mut workload: Workload = toml::from_str(&format!(
+            r#"
+                [user]
+                name = "Test User"
+                email = "test@example.com"
+
+                [[projects]]
+                name = "Test"
+                code = "T1"
+                description = "Test project"
+                git_url = "{}"
+            "#,
+            repo.path().display()
+        ))
+        .unwrap();
+
+        let (base_url, server) = stub_server(vec!["batch 1", "batch 2", "batch 3", "all batches"]).await;
+        workload.llm.base_url = base_url;
+        workload.llm.max_prompt_tokens = 600;
+        workload.llm.api_key_env = "TIMEX_TEST_UNSET_KEY".into();
+
+        let summarizer = OpenAISummarizer::from_llm_config(&workload.llm).unwrap();
+        let report = workload.generate_report(&summarizer).await.unwrap();
+        let requests = server.await.unwrap();
+
+        assert_eq!(report["Test"], "all batches");
+        let prompt = |i: usize| requests[i]["messages"][0]["content"].as_str().unwrap().to_string();
+        assert!(prompt(0).starts_with(MAP_PROMPT));
+        assert!(!prompt(0).contains('\x1b'));
+        assert!(prompt(3).starts_with(REDUCE_PROMPT));
+        assert!(prompt(3).contains("batch 1\nbatch 2\nbatch 3"));
+    }
+}